@@ -0,0 +1,405 @@
+use crate::argon2_hash::{Argon2, Argon2Params};
+use crate::bcrypt_hash::Bcrypt;
+use crate::blake_hash::{BLAKE2B, BLAKE2B_256, BLAKE2S, BLAKE3};
+use crate::crypt_hash::{MD5_CRYPT, SHA256_CRYPT, SHA512_CRYPT};
+use crate::detect;
+use crate::md5_hash::MD5;
+use crate::ntlm_hash::{NTLM, NetNtlmV2};
+use crate::pbkdf2_hash::Pbkdf2;
+use crate::sha1_hash::SHA1;
+use crate::sha2_hash::{SHA224, SHA256, SHA384, SHA512, SHA512_224, SHA512_256};
+use crate::sha3_hash::{KECCAK256, KECCAK512, SHA3_224, SHA3_256, SHA3_384, SHA3_512};
+use crate::yescrypt_hash::Yescrypt;
+use base64::Engine;
+use base64::alphabet::STANDARD;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use serde::{Deserialize, Serialize};
+
+/// A hash function the search loop can test candidates against.
+pub trait HashAlgorithm: Sync {
+    /// Name used to select the algorithm on the command line.
+    fn name(&self) -> &'static str;
+
+    /// Whether `hash` looks like it was produced by this algorithm.
+    fn identify(&self, hash: &str) -> bool;
+
+    /// Whether `hash` has the shape of this algorithm's hashes, even where
+    /// [`HashAlgorithm::identify`] leaves it to a more common algorithm
+    /// whose hashes look the same.
+    fn matches_shape(&self, hash: &str) -> bool {
+        self.identify(hash)
+    }
+
+    /// Parses the target hash once, before any candidate is checked.
+    fn prepare(&self, hash: &str, options: &HashOptions) -> Result<Box<dyn Target>, String>;
+
+    /// Raw digest of `candidate` for unsalted algorithms, which lets one
+    /// hash per candidate be matched against any number of targets.
+    fn digest(&self, _candidate: &[u8]) -> Option<Digest> {
+        None
+    }
+
+    /// Why hashes of this algorithm cannot be made from a password, `None`
+    /// when [`HashAlgorithm::generate`] can make them.
+    fn generation_error(&self) -> Option<String> {
+        Some(format!(
+            "'{}' cannot generate hashes, only verify them",
+            self.name()
+        ))
+    }
+
+    /// Hashes `password` with the settings of `options` that apply to this
+    /// algorithm, to make targets and test vectors.
+    fn generate(&self, _password: &[u8], _options: &GenerateOptions) -> Result<String, String> {
+        Err(self
+            .generation_error()
+            .unwrap_or_else(|| format!("'{}' cannot generate hashes", self.name())))
+    }
+}
+
+/// Settings that apply to every target of a run but are not recorded in the
+/// hashes themselves.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct HashOptions {
+    /// Key mixed into every hash, also known as a pepper (Argon2). It is
+    /// never serialized, so session files do not give it away.
+    #[serde(skip)]
+    pub secret: Option<Vec<u8>>,
+    /// Data hashed along with every password (Argon2). A PHC string's own
+    /// `data` parameter takes precedence.
+    pub associated_data: Option<Vec<u8>>,
+    /// Parameters of raw Argon2 targets, given as `<salt hex>:<output hex>`
+    /// pairs rather than PHC strings.
+    pub argon2: Option<Argon2Params>,
+}
+
+/// Settings of generated hashes. Each algorithm reads the ones that apply to
+/// it and records them in the hash.
+#[derive(Clone)]
+pub struct GenerateOptions {
+    /// Argon2 variant, bcrypt variant or PBKDF2 hash function, the
+    /// algorithm's default when `None`.
+    pub variant: Option<String>,
+    /// bcrypt cost.
+    pub cost: u32,
+    /// SHA-crypt rounds or PBKDF2 iterations, the algorithm's default when
+    /// `None`.
+    pub rounds: Option<u32>,
+    /// Salt as given on the command line, random when `None`.
+    pub salt: Option<String>,
+    /// Argon2 memory in KiB, iterations and lanes.
+    pub argon2_params: (u32, u32, u32),
+    /// Argon2 version, 16 or 19.
+    pub argon2_version: u32,
+    /// Argon2 output length in bytes.
+    pub output_len: usize,
+    /// Whether Argon2 hashes are written as `<salt hex>:<output hex>`
+    /// rather than PHC strings.
+    pub raw: bool,
+    /// yescrypt parameters in the notation of the hash, such as `j9T`.
+    pub yescrypt_params: String,
+    /// PBKDF2 encoding: `django`, `passlib` or `phc`.
+    pub pbkdf2_format: String,
+    /// Argon2 secret and associated data.
+    pub hash_options: HashOptions,
+}
+
+/// A target hash in the form its algorithm checks candidates against.
+pub trait Target: Sync {
+    fn check(&self, candidate: &[u8]) -> bool;
+
+    /// Expected raw digest for targets of unsalted algorithms.
+    fn digest(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Bytes of working memory one check allocates, for the memory-hard
+    /// algorithms whose cost matters to how many can run at once.
+    fn memory(&self) -> u64 {
+        0
+    }
+}
+
+/// Longest raw digest an unsalted algorithm can produce.
+pub const MAX_DIGEST_LEN: usize = 64;
+
+/// A raw digest stored inline, so hashing a candidate never allocates.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Digest {
+    bytes: [u8; MAX_DIGEST_LEN],
+    len: usize,
+}
+
+impl Digest {
+    pub fn new(bytes: &[u8]) -> Self {
+        let mut digest = Digest {
+            bytes: [0; MAX_DIGEST_LEN],
+            len: bytes.len(),
+        };
+        digest.bytes[..bytes.len()].copy_from_slice(bytes);
+        digest
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+/// Target of an unsalted algorithm: the expected digest, decoded once, and
+/// the function producing digests of candidates.
+pub struct DigestTarget {
+    expected: Digest,
+    hash: fn(&[u8]) -> Digest,
+}
+
+impl DigestTarget {
+    /// Decodes a digest of `len` bytes, see [`decode_digest`].
+    pub fn parse(encoded: &str, len: usize, hash: fn(&[u8]) -> Digest) -> Option<Self> {
+        let bytes = decode_digest(encoded, len)?;
+        Some(DigestTarget {
+            expected: Digest::new(&bytes),
+            hash,
+        })
+    }
+}
+
+/// Standard base64, padded or not, as applications store raw digests.
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Decodes a digest of `len` bytes written in hex or base64. Strings of hex
+/// digits only are never read as base64, so a hex SHA-256 digest is not
+/// taken for a base64 SHA-384 one.
+pub fn decode_digest(hash: &str, len: usize) -> Option<Vec<u8>> {
+    if detect::is_hex(hash, len * 2) {
+        return hex::decode(hash).ok();
+    }
+    if hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    BASE64.decode(hash).ok().filter(|bytes| bytes.len() == len)
+}
+
+impl Target for DigestTarget {
+    fn check(&self, candidate: &[u8]) -> bool {
+        (self.hash)(candidate) == self.expected
+    }
+
+    fn digest(&self) -> Option<&[u8]> {
+        Some(self.expected.as_bytes())
+    }
+}
+
+/// An unsalted algorithm producing digests of `len` bytes, given in hex or
+/// base64.
+pub struct DigestAlgorithm {
+    pub(crate) name: &'static str,
+    pub(crate) label: &'static str,
+    pub(crate) len: usize,
+    pub(crate) hash: fn(&[u8]) -> Digest,
+    /// Whether detection picks the algorithm for digests of its length.
+    /// Only one algorithm per length does; the others need `--algo`.
+    pub(crate) detect: bool,
+}
+
+/// Digest of `input` with a RustCrypto hash function.
+pub(crate) fn hash_with<D: digest::Digest>(input: &[u8]) -> Digest {
+    Digest::new(&D::digest(input))
+}
+
+impl HashAlgorithm for DigestAlgorithm {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn identify(&self, hash: &str) -> bool {
+        self.detect && self.matches_shape(hash)
+    }
+
+    fn matches_shape(&self, hash: &str) -> bool {
+        decode_digest(hash, self.len).is_some()
+    }
+
+    fn prepare(&self, hash: &str, _options: &HashOptions) -> Result<Box<dyn Target>, String> {
+        let target = DigestTarget::parse(hash, self.len, self.hash).ok_or_else(|| {
+            format!(
+                "{} hash must be {} hex or {} base64 characters",
+                self.label,
+                self.len * 2,
+                self.len.div_ceil(3) * 4
+            )
+        })?;
+        Ok(Box::new(target))
+    }
+
+    fn digest(&self, candidate: &[u8]) -> Option<Digest> {
+        Some((self.hash)(candidate))
+    }
+
+    fn generation_error(&self) -> Option<String> {
+        None
+    }
+
+    fn generate(&self, password: &[u8], _options: &GenerateOptions) -> Result<String, String> {
+        Ok(hex::encode((self.hash)(password).as_bytes()))
+    }
+}
+
+/// Every algorithm that can be selected at run time.
+pub static ALGORITHMS: &[&dyn HashAlgorithm] = &[
+    &MD5,
+    &SHA1,
+    &SHA224,
+    &SHA256,
+    &SHA384,
+    &SHA512,
+    &SHA512_224,
+    &SHA512_256,
+    &SHA3_224,
+    &SHA3_256,
+    &SHA3_384,
+    &SHA3_512,
+    &KECCAK256,
+    &KECCAK512,
+    &BLAKE2B,
+    &BLAKE2B_256,
+    &BLAKE2S,
+    &BLAKE3,
+    &NTLM,
+    &NetNtlmV2,
+    &Bcrypt,
+    &Argon2,
+    &MD5_CRYPT,
+    &SHA256_CRYPT,
+    &SHA512_CRYPT,
+    &Yescrypt,
+    &Pbkdf2,
+];
+
+pub fn find(name: &str) -> Option<&'static dyn HashAlgorithm> {
+    ALGORITHMS
+        .iter()
+        .copied()
+        .find(|algo| algo.name().eq_ignore_ascii_case(name))
+}
+
+pub fn names() -> Vec<&'static str> {
+    ALGORITHMS.iter().map(|algo| algo.name()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SHA-256 of "abc".
+    const SHA256_ABC: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn decodes_hex_and_base64_digests() {
+        let expected = hex::decode(SHA256_ABC).unwrap();
+        for encoded in [
+            SHA256_ABC,
+            "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD",
+            "ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=",
+            "ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0",
+        ] {
+            assert_eq!(
+                decode_digest(encoded, 32),
+                Some(expected.clone()),
+                "{}",
+                encoded
+            );
+        }
+        // MD5 of "abc" in base64.
+        assert_eq!(
+            decode_digest("kAFQmDzST7DWlj99KOF/cg==", 16),
+            Some(hex::decode("900150983cd24fb0d6963f7d28e17f72").unwrap())
+        );
+    }
+
+    #[test]
+    fn rejects_digests_of_other_lengths() {
+        for (encoded, len) in [
+            // 64 hex digits are valid base64 for 48 bytes, but never a
+            // SHA-384 digest.
+            (SHA256_ABC, 48),
+            (&SHA256_ABC[..62], 32),
+            // Base64 of 32 bytes read as a 48-byte digest.
+            ("ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=", 48),
+            ("ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=", 16),
+            ("not base64!", 8),
+        ] {
+            assert_eq!(
+                decode_digest(encoded, len),
+                None,
+                "{} as {} bytes",
+                encoded,
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn checks_base64_targets() {
+        let base64 = "ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=";
+        assert!(SHA256.identify(base64));
+        assert!(!SHA384.identify(SHA256_ABC));
+        assert!(!SHA384.matches_shape(SHA256_ABC));
+        let target = SHA256.prepare(base64, &HashOptions::default()).unwrap();
+        assert!(target.check(b"abc"));
+        assert!(!target.check(b"abd"));
+        assert_eq!(
+            target.digest().unwrap(),
+            &hex::decode(SHA256_ABC).unwrap()[..]
+        );
+        let error = SHA384
+            .prepare(SHA256_ABC, &HashOptions::default())
+            .err()
+            .unwrap();
+        assert_eq!(error, "SHA-384 hash must be 96 hex or 64 base64 characters");
+    }
+
+    #[test]
+    fn generates_hashes_every_algorithm_verifies() {
+        let options = GenerateOptions {
+            variant: None,
+            cost: 4,
+            rounds: Some(1000),
+            salt: None,
+            argon2_params: (64, 1, 1),
+            argon2_version: 0x13,
+            output_len: 32,
+            raw: false,
+            yescrypt_params: "j9T".to_string(),
+            pbkdf2_format: "phc".to_string(),
+            hash_options: HashOptions::default(),
+        };
+        for algo in ALGORITHMS {
+            let options = match algo.name() {
+                // md5crypt always runs 1000 rounds and takes no count.
+                "md5crypt" => GenerateOptions {
+                    rounds: None,
+                    ..options.clone()
+                },
+                _ => options.clone(),
+            };
+            let generated = algo.generate(b"password", &options);
+            match algo.generation_error() {
+                Some(error) => assert_eq!(generated, Err(error), "{}", algo.name()),
+                None => {
+                    let hash = generated.unwrap();
+                    let target = algo.prepare(&hash, &HashOptions::default()).unwrap();
+                    assert!(target.check(b"password"), "{}: {}", algo.name(), hash);
+                    assert!(!target.check(b"Password"), "{}: {}", algo.name(), hash);
+                }
+            }
+        }
+        assert!(
+            crate::crypt_hash::MD5_CRYPT
+                .generate(b"", &options)
+                .is_err()
+        );
+    }
+}
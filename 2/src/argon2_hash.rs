@@ -1,12 +1,609 @@
-use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use crate::algorithm::{GenerateOptions, HashAlgorithm, HashOptions, Target};
+use crate::detect;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{Salt, SaltString};
+use argon2::{AssociatedData, Block, ParamsBuilder, PasswordHash, PasswordHasher, Version};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::str::FromStr;
 
-pub fn verify_argon2(password: &str, hash: &str) -> bool {
-    let parsed_hash = match PasswordHash::new(hash) {
-        Ok(h) => h,
-        Err(_) => return false,
+pub struct Argon2;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Variant {
+    Argon2d,
+    Argon2i,
+    Argon2id,
+}
+
+impl Variant {
+    fn algorithm(self) -> argon2::Algorithm {
+        match self {
+            Variant::Argon2d => argon2::Algorithm::Argon2d,
+            Variant::Argon2i => argon2::Algorithm::Argon2i,
+            Variant::Argon2id => argon2::Algorithm::Argon2id,
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "argon2d" => Ok(Variant::Argon2d),
+            "argon2i" => Ok(Variant::Argon2i),
+            "argon2id" => Ok(Variant::Argon2id),
+            _ => Err(format!(
+                "Unknown Argon2 variant '{}', expected argon2d, argon2i or argon2id",
+                s
+            )),
+        }
+    }
+}
+
+/// The parameters a PHC string records in its header: for raw hashes they
+/// are given separately.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Argon2Params {
+    pub variant: Variant,
+    /// 16 (0x10) or 19 (0x13).
+    pub version: u32,
+    /// Memory in KiB.
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for Argon2Params {
+    fn default() -> Self {
+        Argon2Params {
+            variant: Variant::Argon2id,
+            version: 0x13,
+            m_cost: argon2::Params::DEFAULT_M_COST,
+            t_cost: argon2::Params::DEFAULT_T_COST,
+            p_cost: argon2::Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// A target decoded once: everything needed to hash a candidate the way the
+/// target was hashed, and the expected output.
+struct Argon2Target {
+    algorithm: argon2::Algorithm,
+    version: Version,
+    params: argon2::Params,
+    secret: Option<Vec<u8>>,
+    salt: Vec<u8>,
+    expected: Vec<u8>,
+}
+
+thread_local! {
+    /// Working memory of the checks run on this thread, kept from one check
+    /// to the next rather than allocated for each. It grows to the largest
+    /// memory cost checked and lives as long as the thread: jobs checking
+    /// Argon2 hashes run on threads of their own, which end with the job.
+    static BLOCKS: RefCell<Vec<Block>> = const { RefCell::new(Vec::new()) };
+}
+
+impl Argon2Target {
+    /// Hashes `password` into `out`, working in `blocks`, which grow to the
+    /// memory cost if smaller.
+    fn hash_into(
+        &self,
+        password: &[u8],
+        out: &mut [u8],
+        blocks: &mut Vec<Block>,
+    ) -> Result<(), String> {
+        let context = context(
+            self.algorithm,
+            self.version,
+            &self.params,
+            self.secret.as_deref(),
+        )?;
+        let count = self.params.block_count();
+        if blocks.len() < count {
+            blocks.resize(count, Block::default());
+        }
+        context
+            .hash_password_into_with_memory(password, &self.salt, out, &mut blocks[..count])
+            .map_err(|e| format!("Argon2: {}", e))
+    }
+
+    fn check_with(&self, candidate: &[u8], blocks: &mut Vec<Block>) -> bool {
+        let mut output = vec![0u8; self.expected.len()];
+        self.hash_into(candidate, &mut output, blocks).is_ok() && output == self.expected
+    }
+}
+
+fn context<'a>(
+    algorithm: argon2::Algorithm,
+    version: Version,
+    params: &argon2::Params,
+    secret: Option<&'a [u8]>,
+) -> Result<argon2::Argon2<'a>, String> {
+    match secret {
+        Some(secret) => argon2::Argon2::new_with_secret(secret, algorithm, version, params.clone())
+            .map_err(|e| format!("Invalid Argon2 secret: {}", e)),
+        None => Ok(argon2::Argon2::new(algorithm, version, params.clone())),
+    }
+}
+
+/// Parses a PHC string, taking the associated data from `options` unless the
+/// hash records its own. A string without `v=` is version 16, as libargon2
+/// wrote them before version 19 added the field.
+fn parse_phc(hash: &str, options: &HashOptions) -> Result<Argon2Target, String> {
+    let parsed = PasswordHash::new(hash).map_err(|e| format!("Invalid Argon2 hash: {}", e))?;
+    let algorithm = argon2::Algorithm::try_from(parsed.algorithm)
+        .map_err(|e| format!("Invalid Argon2 hash: {}", e))?;
+    let version = Version::try_from(parsed.version.unwrap_or(0x10))
+        .map_err(|e| format!("Invalid Argon2 hash: {}", e))?;
+    let mut params =
+        argon2::Params::try_from(&parsed).map_err(|e| format!("Invalid Argon2 hash: {}", e))?;
+    if params.data().is_empty()
+        && let Some(data) = &options.associated_data
+    {
+        params = with_data(&params, data, params.output_len())?;
+    }
+
+    let salt = parsed.salt.ok_or("Argon2 hash has no salt")?;
+    let mut salt_buf = [0u8; Salt::MAX_LENGTH];
+    let salt = salt
+        .decode_b64(&mut salt_buf)
+        .map_err(|e| format!("Invalid Argon2 salt: {}", e))?;
+    let expected = parsed.hash.ok_or("Argon2 hash has no output")?;
+
+    Ok(Argon2Target {
+        algorithm,
+        version,
+        params,
+        secret: options.secret.clone(),
+        salt: salt.to_vec(),
+        expected: expected.as_bytes().to_vec(),
+    })
+}
+
+/// Parses a raw `<salt hex>:<output hex>` pair hashed with `params`.
+fn parse_raw(
+    hash: &str,
+    params: &Argon2Params,
+    options: &HashOptions,
+) -> Result<Argon2Target, String> {
+    let (salt, expected) = hash
+        .split_once(':')
+        .ok_or("Raw Argon2 hash must be <salt hex>:<output hex>")?;
+    let salt = hex::decode(salt).map_err(|e| format!("Invalid Argon2 salt: {}", e))?;
+    let expected = hex::decode(expected).map_err(|e| format!("Invalid Argon2 output: {}", e))?;
+    if salt.len() < argon2::MIN_SALT_LEN {
+        return Err(format!(
+            "Argon2 salt must be at least {} bytes",
+            argon2::MIN_SALT_LEN
+        ));
+    }
+
+    Ok(Argon2Target {
+        algorithm: params.variant.algorithm(),
+        version: version(params.version)?,
+        params: build_params(params, options, expected.len())?,
+        secret: options.secret.clone(),
+        salt,
+        expected,
+    })
+}
+
+fn version(version: u32) -> Result<Version, String> {
+    Version::try_from(version)
+        .map_err(|_| format!("Unknown Argon2 version {}, expected 16 or 19", version))
+}
+
+fn build_params(
+    params: &Argon2Params,
+    options: &HashOptions,
+    output_len: usize,
+) -> Result<argon2::Params, String> {
+    let mut builder = ParamsBuilder::new();
+    builder
+        .m_cost(params.m_cost)
+        .t_cost(params.t_cost)
+        .p_cost(params.p_cost)
+        .output_len(output_len);
+    if let Some(data) = &options.associated_data {
+        builder.data(associated_data(data)?);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Invalid Argon2 parameters: {}", e))
+}
+
+fn with_data(
+    params: &argon2::Params,
+    data: &[u8],
+    output_len: Option<usize>,
+) -> Result<argon2::Params, String> {
+    let mut builder = ParamsBuilder::new();
+    builder
+        .m_cost(params.m_cost())
+        .t_cost(params.t_cost())
+        .p_cost(params.p_cost())
+        .data(associated_data(data)?);
+    if let Some(len) = output_len {
+        builder.output_len(len);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Invalid Argon2 parameters: {}", e))
+}
+
+fn associated_data(data: &[u8]) -> Result<AssociatedData, String> {
+    AssociatedData::new(data).map_err(|_| {
+        format!(
+            "Argon2 associated data must be at most {} bytes",
+            AssociatedData::MAX_LEN
+        )
+    })
+}
+
+/// Checks `password` against a PHC string.
+pub fn verify_argon2(password: &[u8], hash: &str) -> bool {
+    parse_phc(hash, &HashOptions::default())
+        .is_ok_and(|target| target.check_with(password, &mut Vec::new()))
+}
+
+/// Hashes `password` with `params` into an output of `output_len` bytes,
+/// salted with `salt` or a random 16 bytes. Returns the salt and the output.
+pub fn hash_argon2_raw(
+    password: &[u8],
+    params: &Argon2Params,
+    options: &HashOptions,
+    salt: Option<&[u8]>,
+    output_len: usize,
+) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let salt = match salt {
+        Some(salt) => salt.to_vec(),
+        None => {
+            let mut salt = vec![0u8; argon2::RECOMMENDED_SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            salt
+        }
+    };
+    if salt.len() < argon2::MIN_SALT_LEN {
+        return Err(format!(
+            "Argon2 salt must be at least {} bytes",
+            argon2::MIN_SALT_LEN
+        ));
+    }
+    let target = Argon2Target {
+        algorithm: params.variant.algorithm(),
+        version: version(params.version)?,
+        params: build_params(params, options, output_len)?,
+        secret: options.secret.clone(),
+        salt,
+        expected: Vec::new(),
     };
+    let mut output = vec![0u8; output_len];
+    target.hash_into(password, &mut output, &mut Vec::new())?;
+    Ok((target.salt, output))
+}
+
+/// Hashes `password` into a PHC string, see [`hash_argon2_raw`]. The secret
+/// is not part of the string; associated data is, as its `data` parameter.
+pub fn hash_argon2(
+    password: &[u8],
+    params: &Argon2Params,
+    options: &HashOptions,
+    salt: Option<&[u8]>,
+    output_len: usize,
+) -> Result<String, String> {
+    let salt = match salt {
+        Some(salt) if salt.len() < argon2::MIN_SALT_LEN => {
+            return Err(format!(
+                "Argon2 salt must be at least {} bytes",
+                argon2::MIN_SALT_LEN
+            ));
+        }
+        Some(salt) => {
+            SaltString::encode_b64(salt).map_err(|e| format!("Invalid Argon2 salt: {}", e))?
+        }
+        None => SaltString::generate(&mut OsRng),
+    };
+    context(
+        params.variant.algorithm(),
+        version(params.version)?,
+        &build_params(params, options, output_len)?,
+        options.secret.as_deref(),
+    )?
+    .hash_password(password, &salt)
+    .map(|hash| hash.to_string())
+    .map_err(|e| format!("Argon2: {}", e))
+}
+
+impl HashAlgorithm for Argon2 {
+    fn name(&self) -> &'static str {
+        "argon2"
+    }
+
+    fn identify(&self, hash: &str) -> bool {
+        matches!(
+            detect::crypt_id(hash),
+            Some("argon2d" | "argon2i" | "argon2id")
+        )
+    }
+
+    fn prepare(&self, hash: &str, options: &HashOptions) -> Result<Box<dyn Target>, String> {
+        let target = match &options.argon2 {
+            Some(params) => parse_raw(hash, params, options)?,
+            None => parse_phc(hash, options)?,
+        };
+        Ok(Box::new(target))
+    }
+
+    fn generation_error(&self) -> Option<String> {
+        None
+    }
+
+    fn generate(&self, password: &[u8], options: &GenerateOptions) -> Result<String, String> {
+        let (m_cost, t_cost, p_cost) = options.argon2_params;
+        let params = &Argon2Params {
+            variant: options.variant.as_deref().unwrap_or("argon2id").parse()?,
+            version: options.argon2_version,
+            m_cost,
+            t_cost,
+            p_cost,
+        };
+        let hash_options = &options.hash_options;
+        let salt = options.salt.as_deref().map(str::as_bytes);
+        if options.raw {
+            let (salt, output) =
+                hash_argon2_raw(password, params, hash_options, salt, options.output_len)?;
+            Ok(format!("{}:{}", hex::encode(salt), hex::encode(output)))
+        } else {
+            hash_argon2(password, params, hash_options, salt, options.output_len)
+        }
+    }
+}
+
+impl Target for Argon2Target {
+    fn check(&self, candidate: &[u8]) -> bool {
+        BLOCKS.with_borrow_mut(|blocks| self.check_with(candidate, blocks))
+    }
+
+    fn memory(&self) -> u64 {
+        (self.params.block_count() * Block::SIZE) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Outputs of the RFC 9106 vectors, and of its draft for version 16:
+    /// 32 bytes of 0x01 hashed with 16 bytes of 0x02 as salt, 8 of 0x03 as
+    /// secret and 12 of 0x04 as associated data, m=32, t=3, p=4.
+    const VECTORS: &[(Variant, u32, &str)] = &[
+        (
+            Variant::Argon2d,
+            0x10,
+            "96a9d4e5a1734092c85e29f410a45914a5dd1f5cbf08b2670da68a0285abf32b",
+        ),
+        (
+            Variant::Argon2i,
+            0x10,
+            "87aeedd6517ab830cd9765cd8231abb2e647a5dee08f7c05e02fcb763335d0fd",
+        ),
+        (
+            Variant::Argon2id,
+            0x10,
+            "b64615f07789b66b645b67ee9ed3b377ae350b6bfcbb0fc95141ea8f322613c0",
+        ),
+        (
+            Variant::Argon2d,
+            0x13,
+            "512b391b6f1162975371d30919734294f868e3be3984f3c1a13a4db9fabe4acb",
+        ),
+        (
+            Variant::Argon2i,
+            0x13,
+            "c814d9d1dc7f37aa13f0d77f2494bda1c8de6b016dd388d29952a4c4672b6ce8",
+        ),
+        (
+            Variant::Argon2id,
+            0x13,
+            "0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659",
+        ),
+    ];
+
+    const PASSWORD: [u8; 32] = [0x01; 32];
+
+    fn params(variant: Variant, version: u32) -> Argon2Params {
+        Argon2Params {
+            variant,
+            version,
+            m_cost: 32,
+            t_cost: 3,
+            p_cost: 4,
+        }
+    }
+
+    /// The secret of the RFC 9106 vectors.
+    fn secret() -> HashOptions {
+        HashOptions {
+            secret: Some(vec![0x03; 8]),
+            ..HashOptions::default()
+        }
+    }
+
+    /// The secret and associated data of the RFC 9106 vectors.
+    fn secret_and_data() -> HashOptions {
+        HashOptions {
+            associated_data: Some(vec![0x04; 12]),
+            ..secret()
+        }
+    }
+
+    fn check_raw(hash: &str, params: &Argon2Params, options: &HashOptions) -> bool {
+        parse_raw(hash, params, options).is_ok_and(|target| target.check(&PASSWORD))
+    }
+
+    fn check_phc(hash: &str, password: &[u8], options: &HashOptions) -> bool {
+        parse_phc(hash, options).is_ok_and(|target| target.check(password))
+    }
+
+    #[test]
+    fn reports_the_memory_of_a_check() {
+        let hash = "$argon2id$v=19$m=65536,t=2,p=1$c29tZXNhbHQ$CTFhFdXPJO1aFaMaO6Mm5c8y7cJHAph8ArZWb2GRPPc";
+        let target = Argon2.prepare(hash, &HashOptions::default()).unwrap();
+        assert_eq!(target.memory(), 64 << 20);
+        // Memory is rounded down to a multiple of 4 blocks per lane.
+        let raw = Argon2Params {
+            m_cost: 50,
+            ..params(Variant::Argon2id, 0x13)
+        };
+        let target = parse_raw("0202020202020202:00000000", &raw, &secret()).unwrap();
+        assert_eq!(target.memory(), 48 << 10);
+    }
+
+    #[test]
+    fn reads_a_missing_version_as_16() {
+        // libargon2's own version 16 vector, as it encoded them before
+        // version 19 added `v=`.
+        assert!(verify_argon2(
+            b"password",
+            "$argon2i$m=65536,t=2,p=1$c29tZXNhbHQ$9sTbSlTio3Biev89thdrlKKiCaYsjjYVJxGAL3swxpQ"
+        ));
+
+        // The Argon2d vectors of RFC 9106 and its version 16 draft.
+        let v16 = "m=32,t=3,p=4,data=BAQEBAQEBAQEBAQE$AgICAgICAgICAgICAgICAg$lqnU5aFzQJLIXin0EKRZFKXdH1y/CLJnDaaKAoWr8ys";
+        let v19 = "m=32,t=3,p=4,data=BAQEBAQEBAQEBAQE$AgICAgICAgICAgICAgICAg$USs5G28RYpdTcdMJGXNClPho4745hPPBoTpNufq+Sss";
+        let password = [0x01; 32];
+        assert!(check_phc(
+            &format!("$argon2d${}", v16),
+            &password,
+            &secret()
+        ));
+        assert!(check_phc(
+            &format!("$argon2d$v=16${}", v16),
+            &password,
+            &secret()
+        ));
+        assert!(!check_phc(
+            &format!("$argon2d$v=19${}", v16),
+            &password,
+            &secret()
+        ));
+        assert!(check_phc(
+            &format!("$argon2d$v=19${}", v19),
+            &password,
+            &secret()
+        ));
+        assert!(!check_phc(
+            &format!("$argon2d${}", v19),
+            &password,
+            &secret()
+        ));
+    }
+
+    #[test]
+    fn verifies_rfc_9106_vectors() {
+        let salt = hex::encode([0x02; 16]);
+        for &(variant, version, output) in VECTORS {
+            let params = params(variant, version);
+            let hash = format!("{}:{}", salt, output);
+            assert!(check_raw(&hash, &params, &secret_and_data()), "{}", output);
+            assert!(!check_raw(&hash, &params, &secret()), "{}", output);
+            assert!(
+                !check_raw(&hash, &params, &HashOptions::default()),
+                "{}",
+                output
+            );
+            let other_version = Argon2Params {
+                version: 0x10 + 0x13 - version,
+                ..params
+            };
+            assert!(
+                !check_raw(&hash, &other_version, &secret_and_data()),
+                "{}",
+                output
+            );
+        }
+    }
+
+    #[test]
+    fn generates_rfc_9106_vectors() {
+        for &(variant, version, output) in VECTORS {
+            let (salt, hash) = hash_argon2_raw(
+                &PASSWORD,
+                &params(variant, version),
+                &secret_and_data(),
+                Some(&[0x02; 16]),
+                32,
+            )
+            .unwrap();
+            assert_eq!(salt, [0x02; 16]);
+            assert_eq!(hex::encode(hash), output);
+        }
+    }
+
+    #[test]
+    fn round_trips_raw_hashes() {
+        let params = params(Variant::Argon2id, 0x13);
+        for output_len in [4, 16, 64] {
+            for options in [HashOptions::default(), secret(), secret_and_data()] {
+                let (salt, output) =
+                    hash_argon2_raw(&PASSWORD, &params, &options, None, output_len).unwrap();
+                assert_eq!(salt.len(), argon2::RECOMMENDED_SALT_LEN);
+                assert_eq!(output.len(), output_len);
+                let hash = format!("{}:{}", hex::encode(salt), hex::encode(output));
+                assert!(check_raw(&hash, &params, &options), "{}", hash);
+                let target = parse_raw(&hash, &params, &options).unwrap();
+                assert!(!target.check(b"wrong"), "{}", hash);
+            }
+        }
+        for hash in [
+            "0202020202020202",
+            "02020202:00",
+            "zz020202020202:00",
+            "0202020202020202:zz",
+        ] {
+            assert!(
+                parse_raw(hash, &params, &HashOptions::default()).is_err(),
+                "{}",
+                hash
+            );
+        }
+    }
+
+    #[test]
+    fn round_trips_phc_strings() {
+        let params = params(Variant::Argon2i, 0x10);
+        let options = secret_and_data();
+        let hash = hash_argon2(&PASSWORD, &params, &options, Some(&[0x02; 16]), 32).unwrap();
+        // The secret stays out of the string, the associated data goes in.
+        assert_eq!(
+            hash,
+            "$argon2i$v=16$m=32,t=3,p=4,data=BAQEBAQEBAQEBAQE$AgICAgICAgICAgICAgICAg$h67t1lF6uDDNl2XNgjGrsuZHpd7gj3wF4C/LdjM10P0"
+        );
+        assert!(check_phc(&hash, &PASSWORD, &secret()));
+        assert!(!check_phc(&hash, &PASSWORD, &HashOptions::default()));
+    }
+
+    #[test]
+    fn takes_associated_data_from_the_hash_first() {
+        // Generated by the argon2 crate, whose vectors match libargon2's.
+        let hash = "$argon2d$v=16$m=32,t=2,p=3,data=Dw8PDw8P$AAAAAAAAAAA$KnH4gniiaFnDvlA1xev3yovC4cnrrI6tnHOYtmja90o";
+        let other_data = HashOptions {
+            associated_data: Some(b"other".to_vec()),
+            ..HashOptions::default()
+        };
+        assert!(verify_argon2(b"password", hash));
+        assert!(check_phc(hash, b"password", &other_data));
 
-    Argon2::default()
-        .verify_password(password.as_bytes(), &parsed_hash)
-        .is_ok()
+        let without_data = hash.replace(",data=Dw8PDw8P", "");
+        let data = HashOptions {
+            associated_data: Some(vec![0x0f; 6]),
+            ..HashOptions::default()
+        };
+        assert!(!verify_argon2(b"password", &without_data));
+        assert!(check_phc(&without_data, b"password", &data));
+    }
 }
@@ -0,0 +1,57 @@
+//! Sizing of the batches candidates are checked in, by measured cost: a
+//! batch of MD5 holds millions of candidates, one of Argon2 a handful, and
+//! either takes about the same time.
+
+use std::time::Duration;
+
+/// Wall time a batch of the search aims for. Hits, progress, checkpoints,
+/// pausing and cancelling are all handled between batches.
+pub const TARGET_BATCH_TIME: Duration = Duration::from_secs(1);
+
+/// Most a batch grows or shrinks by at once, so one odd measurement does not
+/// throw the size off.
+const MAX_SCALE: f64 = 4.0;
+
+pub struct BatchSizer {
+    size: u64,
+    min: u64,
+    max: u64,
+    target: Duration,
+}
+
+impl BatchSizer {
+    /// Starts at one candidate per thread of the `threads` the batches run
+    /// on and never grows beyond `max` candidates.
+    pub fn new(target: Duration, max: u64, threads: usize) -> Self {
+        let min = (threads as u64).min(max).max(1);
+        BatchSizer {
+            size: min,
+            min,
+            max: max.max(min),
+            target,
+        }
+    }
+
+    /// Number of candidates the next batch should hold.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Scales the size towards the target time after a batch of `done`
+    /// candidates took `elapsed`.
+    pub fn record(&mut self, done: u64, elapsed: Duration) {
+        // The last batch of a range is cut short, and fixed costs weigh too
+        // much in it to say anything about a full one.
+        if done == 0 || done.saturating_mul(2) < self.size {
+            return;
+        }
+        let ideal = if elapsed.is_zero() {
+            f64::INFINITY
+        } else {
+            self.target.as_secs_f64() * done as f64 / elapsed.as_secs_f64()
+        };
+        let current = self.size as f64;
+        let size = ideal.clamp(current / MAX_SCALE, current * MAX_SCALE);
+        self.size = (size as u64).clamp(self.min, self.max);
+    }
+}
@@ -1,3 +1,329 @@
-pub fn verify_bcrypt(password: &str, hash: &str) -> bool {
-    bcrypt::verify(password, hash).unwrap_or(false)
+use crate::algorithm::{GenerateOptions, HashAlgorithm, HashOptions, Target};
+use crate::detect;
+use base64::Engine;
+use base64::alphabet::BCRYPT;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use std::str::FromStr;
+
+pub struct Bcrypt;
+
+/// Longest key bcrypt uses; bytes of a password beyond it are ignored.
+pub const MAX_KEY_LEN: usize = 72;
+const MIN_COST: u32 = 4;
+const MAX_COST: u32 = 31;
+const SALT_LEN: usize = 16;
+/// bcrypt encrypts 24 bytes but the hash keeps only the first 23.
+const DIGEST_LEN: usize = 23;
+
+/// bcrypt's base64 alphabet. Salts of some implementations end in a
+/// character with bits set beyond the 16 bytes, which are ignored.
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &BCRYPT,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_allow_trailing_bits(true)
+        .with_decode_padding_mode(DecodePaddingMode::RequireNone),
+);
+
+/// The revision in a bcrypt hash's prefix.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    /// `$2a$`, hashed like `$2b$` here. OpenBSD's wrapped the length of keys
+    /// over 255 bytes and crypt_blowfish's changes a few hashes of non-ASCII
+    /// passwords, neither of which is reproduced.
+    TwoA,
+    /// `$2b$`, the current revision.
+    TwoB,
+    /// `$2x$`, hashes of crypt_blowfish before 1.1, which sign-extended
+    /// password bytes of 0x80 and above.
+    TwoX,
+    /// `$2y$`, crypt_blowfish's name for the correct algorithm.
+    TwoY,
+}
+
+impl Variant {
+    fn prefix(self) -> &'static str {
+        match self {
+            Variant::TwoA => "2a",
+            Variant::TwoB => "2b",
+            Variant::TwoX => "2x",
+            Variant::TwoY => "2y",
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_matches('$') {
+            "2a" => Ok(Variant::TwoA),
+            "2b" => Ok(Variant::TwoB),
+            "2x" => Ok(Variant::TwoX),
+            "2y" => Ok(Variant::TwoY),
+            _ => Err(format!(
+                "Unknown bcrypt variant '{}', expected 2a, 2b, 2x or 2y",
+                s
+            )),
+        }
+    }
+}
+
+/// A `$2?$cost$salt+digest` hash, decoded once.
+struct BcryptTarget {
+    variant: Variant,
+    cost: u32,
+    salt: [u8; SALT_LEN],
+    digest: [u8; DIGEST_LEN],
+}
+
+fn parse(hash: &str) -> Result<BcryptTarget, String> {
+    let invalid = |reason: String| format!("Invalid bcrypt hash: {}", reason);
+    let mut parts = hash.strip_prefix('$').unwrap_or(hash).split('$');
+    let (Some(variant), Some(cost_digits), Some(rest), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid("expected $2b$<cost>$<salt><digest>".to_string()));
+    };
+    let variant: Variant = variant
+        .parse()
+        .map_err(|_| invalid(format!("unknown variant '{}'", variant)))?;
+    let cost = cost_digits
+        .parse::<u32>()
+        .ok()
+        .filter(|cost| cost_digits.len() == 2 && (MIN_COST..=MAX_COST).contains(cost))
+        .ok_or_else(|| {
+            invalid(format!(
+                "cost '{}' is not two digits in {}..={}",
+                cost_digits, MIN_COST, MAX_COST
+            ))
+        })?;
+    if rest.len() != 53 || !rest.is_ascii() {
+        return Err(invalid(format!(
+            "expected 53 characters of salt and digest, got {}",
+            rest.len()
+        )));
+    }
+    let (salt, digest) = rest.split_at(22);
+    Ok(BcryptTarget {
+        variant,
+        cost,
+        salt: decode(salt).map_err(|e| invalid(format!("salt {}", e)))?,
+        digest: decode(digest).map_err(|e| invalid(format!("digest {}", e)))?,
+    })
+}
+
+fn decode<const N: usize>(encoded: &str) -> Result<[u8; N], String> {
+    let mut out = [0u8; N];
+    match BASE64.decode_slice(encoded, &mut out) {
+        Ok(n) if n == N => Ok(out),
+        Ok(n) => Err(format!("decodes to {} bytes, expected {}", n, N)),
+        Err(e) => Err(format!("is not valid bcrypt base64: {}", e)),
+    }
+}
+
+/// The key bcrypt expands: the password with its terminating NUL, repeated
+/// to fill [`MAX_KEY_LEN`] bytes as the C implementations read it.
+fn key(password: &[u8], variant: Variant) -> [u8; MAX_KEY_LEN] {
+    let mut stream = password.iter().copied().chain(std::iter::once(0)).cycle();
+    let mut key = [0u8; MAX_KEY_LEN];
+    for word in key.chunks_exact_mut(4) {
+        let mut value = 0u32;
+        for byte in stream.by_ref().take(4) {
+            value <<= 8;
+            value |= match variant {
+                Variant::TwoX => byte as i8 as i32 as u32,
+                _ => byte as u32,
+            };
+        }
+        word.copy_from_slice(&value.to_be_bytes());
+    }
+    key
+}
+
+fn hash_raw(password: &[u8], variant: Variant, cost: u32, salt: [u8; SALT_LEN]) -> [u8; 24] {
+    bcrypt::bcrypt(cost, salt, &key(password, variant))
+}
+
+/// Checks `password` against a bcrypt hash of any variant.
+pub fn verify_bcrypt(password: &[u8], hash: &str) -> bool {
+    parse(hash).is_ok_and(|target| target.check(password))
+}
+
+/// Hashes `password` into a hash of the given variant and cost with a
+/// random salt.
+pub fn hash_bcrypt(password: &[u8], variant: Variant, cost: u32) -> Result<String, String> {
+    if !(MIN_COST..=MAX_COST).contains(&cost) {
+        return Err(format!(
+            "bcrypt cost must be in {}..={}",
+            MIN_COST, MAX_COST
+        ));
+    }
+    let mut salt = [0u8; SALT_LEN];
+    getrandom::getrandom(&mut salt).map_err(|e| format!("Failed to generate a salt: {}", e))?;
+    let output = hash_raw(password, variant, cost, salt);
+    Ok(format!(
+        "${}${:02}${}{}",
+        variant.prefix(),
+        cost,
+        BASE64.encode(salt),
+        BASE64.encode(&output[..DIGEST_LEN])
+    ))
+}
+
+impl HashAlgorithm for Bcrypt {
+    fn name(&self) -> &'static str {
+        "bcrypt"
+    }
+
+    fn identify(&self, hash: &str) -> bool {
+        matches!(detect::crypt_id(hash), Some("2a" | "2b" | "2x" | "2y")) && hash.len() == 60
+    }
+
+    fn prepare(&self, hash: &str, _options: &HashOptions) -> Result<Box<dyn Target>, String> {
+        Ok(Box::new(parse(hash)?))
+    }
+
+    fn generation_error(&self) -> Option<String> {
+        None
+    }
+
+    fn generate(&self, password: &[u8], options: &GenerateOptions) -> Result<String, String> {
+        let variant = options.variant.as_deref().unwrap_or("2b").parse()?;
+        hash_bcrypt(password, variant, options.cost)
+    }
+}
+
+impl Target for BcryptTarget {
+    /// Candidates longer than [`MAX_KEY_LEN`] bytes match by their first 72
+    /// bytes, as with any bcrypt implementation.
+    fn check(&self, candidate: &[u8]) -> bool {
+        hash_raw(candidate, self.variant, self.cost, self.salt)[..DIGEST_LEN] == self.digest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test vectors of crypt_blowfish, which John the Ripper and OpenBSD
+    /// share.
+    const VECTORS: &[(&str, &[u8])] = &[
+        (
+            "$2a$05$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW",
+            b"U*U",
+        ),
+        (
+            "$2a$05$CCCCCCCCCCCCCCCCCCCCC.VGOzA784oUp/Z0DY336zx7pLYAy0lwK",
+            b"U*U*",
+        ),
+        (
+            "$2a$05$XXXXXXXXXXXXXXXXXXXXXOAcXxm9kjPGEMsLznoKqmqw7tc8WCx4a",
+            b"U*U*U",
+        ),
+        (
+            "$2a$05$abcdefghijklmnopqrstuu5s2v8.iXieOjg/.AySBTTZIIVFJeBui",
+            b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789chars after 72 are ignored",
+        ),
+        (
+            "$2a$05$CCCCCCCCCCCCCCCCCCCCC.7uG0VCzI2bS7j6ymqJi9CdcdxiRTWNy",
+            b"",
+        ),
+        (
+            "$2b$05$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW",
+            b"U*U",
+        ),
+        (
+            "$2y$05$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW",
+            b"U*U",
+        ),
+        (
+            "$2x$05$/OK.fbVrR/bpIqNJ5ianF.CE5elHaaO4EbggVDjb8P19RukzXSM3e",
+            b"\xa3",
+        ),
+        (
+            "$2y$05$/OK.fbVrR/bpIqNJ5ianF.Sa7shbm4.OzKpvFnX1pQLmQW96oUlCq",
+            b"\xa3",
+        ),
+        (
+            "$2a$05$/OK.fbVrR/bpIqNJ5ianF.Sa7shbm4.OzKpvFnX1pQLmQW96oUlCq",
+            b"\xa3",
+        ),
+    ];
+
+    #[test]
+    fn verifies_known_hashes() {
+        for &(hash, password) in VECTORS {
+            assert!(verify_bcrypt(password, hash), "{}", hash);
+            assert!(!verify_bcrypt(b"wrong", hash), "{}", hash);
+        }
+    }
+
+    #[test]
+    fn reproduces_the_sign_extension_bug_only_for_2x() {
+        let buggy = "$2x$05$/OK.fbVrR/bpIqNJ5ianF.CE5elHaaO4EbggVDjb8P19RukzXSM3e";
+        let correct = "$2y$05$/OK.fbVrR/bpIqNJ5ianF.Sa7shbm4.OzKpvFnX1pQLmQW96oUlCq";
+        assert!(!verify_bcrypt(b"\xa3", &buggy.replacen("2x", "2y", 1)));
+        assert!(!verify_bcrypt(b"\xa3", &correct.replacen("2y", "2x", 1)));
+        // Below 0x80 there is nothing to sign-extend.
+        assert_eq!(key(b"U*U", Variant::TwoX), key(b"U*U", Variant::TwoB));
+    }
+
+    #[test]
+    fn key_repeats_password_and_nul() {
+        let key = key(b"ab", Variant::TwoB);
+        assert_eq!(&key[..7], b"ab\0ab\0a");
+        assert_eq!(key[MAX_KEY_LEN - 1], 0);
+    }
+
+    #[test]
+    fn ignores_bytes_past_72() {
+        let password = [b'p'; MAX_KEY_LEN];
+        let hash = hash_bcrypt(&password, Variant::TwoB, MIN_COST).unwrap();
+        let mut longer = password.to_vec();
+        longer.push(b'x');
+        assert!(verify_bcrypt(&longer, &hash));
+        assert!(!verify_bcrypt(&password[..MAX_KEY_LEN - 1], &hash));
+    }
+
+    #[test]
+    fn round_trips_every_variant() {
+        for variant in ["2a", "2b", "2x", "2y"] {
+            let hash = hash_bcrypt(b"secret", variant.parse().unwrap(), MIN_COST).unwrap();
+            assert!(hash.starts_with(&format!("${}$04$", variant)), "{}", hash);
+            assert_eq!(hash.len(), 60);
+            assert!(verify_bcrypt(b"secret", &hash), "{}", hash);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_hashes() {
+        let valid = "$2b$05$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW";
+        let three_digit_cost = valid.replacen("$05$", "$005$", 1);
+        let short_salt = valid.replacen("CC", "C", 1);
+        let cases = [
+            (three_digit_cost.as_str(), "cost '005'"),
+            (
+                "$2b$03$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW",
+                "cost '03'",
+            ),
+            (short_salt.as_str(), "expected 53 characters"),
+            (
+                "$2c$05$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW",
+                "unknown variant",
+            ),
+            (
+                "$2b$05$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOe!",
+                "digest",
+            ),
+        ];
+        for (hash, reason) in cases {
+            match parse(hash) {
+                Ok(_) => panic!("{} parsed", hash),
+                Err(e) => assert!(e.contains(reason), "{}: {}", hash, e),
+            }
+        }
+        assert!(!Bcrypt.identify(&three_digit_cost));
+        assert!(hash_bcrypt(b"x", Variant::TwoB, 32).is_err());
+    }
 }
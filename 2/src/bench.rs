@@ -0,0 +1,152 @@
+//! Throughput of each algorithm on this machine, for comparing machines and
+//! catching regressions in the search loop.
+
+use crate::algorithm::{self, HashAlgorithm, HashOptions};
+use crate::argon2_hash::{Argon2Params, Variant};
+use crate::batch::BatchSizer;
+use crate::brute::MAX_CANDIDATE_LEN;
+use crate::mask::Mask;
+use crate::targets::TargetSet;
+use crate::{argon2_hash, bcrypt_hash};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Password of the benchmark targets; the candidates, lowercase letters
+/// only, never match it, so every run checks candidates for its whole time.
+const PASSWORD: &[u8] = b"bench-password";
+const CANDIDATE_LEN: usize = 8;
+
+/// Wall time of a batch; shorter batches overshoot the duration less,
+/// longer ones cost less in rayon overhead.
+const BATCH_TIME: Duration = Duration::from_millis(100);
+
+/// An algorithm with the cost parameters of the target hashes.
+#[derive(Clone, Copy)]
+pub enum BenchAlgorithm {
+    /// An algorithm without parameters, such as MD5 or SHA-256.
+    Unsalted(&'static dyn HashAlgorithm),
+    Bcrypt {
+        cost: u32,
+    },
+    Argon2 {
+        m_cost: u32,
+        t_cost: u32,
+        p_cost: u32,
+    },
+}
+
+impl BenchAlgorithm {
+    fn algorithm(&self) -> &'static dyn HashAlgorithm {
+        let name = match self {
+            BenchAlgorithm::Unsalted(algo) => return *algo,
+            BenchAlgorithm::Bcrypt { .. } => "bcrypt",
+            BenchAlgorithm::Argon2 { .. } => "argon2",
+        };
+        algorithm::find(name).expect("benchmarked algorithms are registered")
+    }
+
+    /// Cost parameters in the notation of the hash, empty for unsalted
+    /// algorithms.
+    pub fn params(&self) -> String {
+        match self {
+            BenchAlgorithm::Unsalted(_) => String::new(),
+            BenchAlgorithm::Bcrypt { cost } => format!("cost={}", cost),
+            BenchAlgorithm::Argon2 {
+                m_cost,
+                t_cost,
+                p_cost,
+            } => format!("m={},t={},p={}", m_cost, t_cost, p_cost),
+        }
+    }
+
+    fn target_hash(&self) -> Result<String, String> {
+        match *self {
+            BenchAlgorithm::Unsalted(algo) => algo
+                .digest(PASSWORD)
+                .map(|digest| hex::encode(digest.as_bytes()))
+                .ok_or_else(|| format!("{} is not an unsalted algorithm", algo.name())),
+            BenchAlgorithm::Bcrypt { cost } => {
+                bcrypt_hash::hash_bcrypt(PASSWORD, bcrypt_hash::Variant::TwoB, cost)
+            }
+            BenchAlgorithm::Argon2 {
+                m_cost,
+                t_cost,
+                p_cost,
+            } => argon2_hash::hash_argon2(
+                PASSWORD,
+                &Argon2Params {
+                    variant: Variant::Argon2id,
+                    m_cost,
+                    t_cost,
+                    p_cost,
+                    ..Argon2Params::default()
+                },
+                &HashOptions::default(),
+                Some(b"bench-salt"),
+                argon2::Params::DEFAULT_OUTPUT_LEN,
+            ),
+        }
+    }
+}
+
+/// Throughput measured for one algorithm and thread count.
+#[derive(Clone, Serialize)]
+pub struct BenchResult {
+    pub algorithm: &'static str,
+    pub params: String,
+    pub threads: usize,
+    /// Candidates checked.
+    pub hashes: u64,
+    pub seconds: f64,
+    /// Candidates checked per second.
+    pub rate: f64,
+}
+
+/// Checks candidates against a target of `algorithm` on `threads` threads
+/// for about `duration`, the way a search does.
+pub fn run(
+    algorithm: BenchAlgorithm,
+    threads: usize,
+    duration: Duration,
+) -> Result<BenchResult, String> {
+    let algo = algorithm.algorithm();
+    let mut targets = TargetSet::new(algo);
+    targets.insert(&algorithm.target_hash()?)?;
+    let mask = Mask::uniform(b"abcdefghijklmnopqrstuvwxyz", CANDIDATE_LEN);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| format!("Failed to start {} threads: {}", threads, e))?;
+
+    let start = Instant::now();
+    let mut done = 0u64;
+    pool.install(|| {
+        let mut sizer = BatchSizer::new(BATCH_TIME, u64::MAX, threads);
+        while start.elapsed() < duration {
+            let batch = sizer.size();
+            let batch_start = Instant::now();
+            (done..done + batch).into_par_iter().for_each_init(
+                || [0u8; MAX_CANDIDATE_LEN],
+                |buf, idx| {
+                    let candidate = &mut buf[..CANDIDATE_LEN];
+                    mask.index_to_candidate(idx, candidate);
+                    black_box(targets.matches(candidate));
+                },
+            );
+            done += batch;
+            sizer.record(batch, batch_start.elapsed());
+        }
+    });
+
+    let seconds = start.elapsed().as_secs_f64();
+    Ok(BenchResult {
+        algorithm: algo.name(),
+        params: algorithm.params(),
+        threads,
+        hashes: done,
+        seconds,
+        rate: done as f64 / seconds,
+    })
+}
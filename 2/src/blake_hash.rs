@@ -0,0 +1,84 @@
+//! BLAKE2 and BLAKE3, unkeyed. Their digests look like SHA-2 ones of the
+//! same length, so only `--algo` selects them.
+
+use crate::algorithm::{Digest, DigestAlgorithm, hash_with};
+use blake2::digest::consts::U32;
+
+pub static BLAKE2B: DigestAlgorithm = DigestAlgorithm {
+    name: "blake2b",
+    label: "BLAKE2b-512",
+    len: 64,
+    hash: hash_with::<blake2::Blake2b512>,
+    detect: false,
+};
+
+pub static BLAKE2B_256: DigestAlgorithm = DigestAlgorithm {
+    name: "blake2b-256",
+    label: "BLAKE2b-256",
+    len: 32,
+    hash: hash_with::<blake2::Blake2b<U32>>,
+    detect: false,
+};
+
+pub static BLAKE2S: DigestAlgorithm = DigestAlgorithm {
+    name: "blake2s",
+    label: "BLAKE2s-256",
+    len: 32,
+    hash: hash_with::<blake2::Blake2s256>,
+    detect: false,
+};
+
+pub static BLAKE3: DigestAlgorithm = DigestAlgorithm {
+    name: "blake3",
+    label: "BLAKE3",
+    len: 32,
+    hash: hash_blake3,
+    detect: false,
+};
+
+fn hash_blake3(input: &[u8]) -> Digest {
+    Digest::new(blake3::hash(input).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::algorithm::{self, HashOptions};
+
+    /// Digests of `""` and `"abc"`, from RFC 7693 and the BLAKE3
+    /// reference implementation.
+    const VECTORS: &[(&str, &str, &str)] = &[
+        (
+            "blake2b",
+            "786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce",
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+        ),
+        (
+            "blake2b-256",
+            "0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8",
+            "bddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319",
+        ),
+        (
+            "blake2s",
+            "69217a3079908094e11121d042354a7c1f55b6482ca1a51e1b250dfd1ed0eef9",
+            "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982",
+        ),
+        (
+            "blake3",
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262",
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+        ),
+    ];
+
+    #[test]
+    fn hashes_known_vectors() {
+        for &(name, empty, abc) in VECTORS {
+            let algo = algorithm::find(name).unwrap();
+            for (input, expected) in [(&b""[..], empty), (b"abc", abc)] {
+                let digest = algo.digest(input).unwrap();
+                assert_eq!(hex::encode(digest.as_bytes()), expected, "{}", name);
+                let target = algo.prepare(expected, &HashOptions::default()).unwrap();
+                assert!(target.check(input), "{}", name);
+            }
+        }
+    }
+}
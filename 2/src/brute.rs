@@ -0,0 +1,165 @@
+use crate::batch::{BatchSizer, TARGET_BATCH_TIME};
+use crate::cracker::{self, Cracker};
+use crate::engine::Event;
+use crate::mask::Mask;
+use crate::session::Position;
+use rayon::prelude::*;
+use std::ops::{Range, RangeInclusive};
+use std::time::Instant;
+
+/// Longest candidate the search loop generates.
+pub const MAX_CANDIDATE_LEN: usize = 64;
+
+/// One mask per length, applying the charset to every position.
+pub fn brute_masks(charset: &[u8], lengths: RangeInclusive<usize>) -> Vec<Mask> {
+    lengths
+        .map(|length| Mask::uniform(charset, length))
+        .collect()
+}
+
+/// The mask, preceded with `increment` by each of its prefixes from
+/// shortest to longest.
+pub fn increment_masks(mask: &Mask, increment: bool) -> Vec<Mask> {
+    let first = if increment { 1 } else { mask.len() };
+    (first..=mask.len())
+        .map(|length| mask.prefix(length))
+        .collect()
+}
+
+/// Enumerates the part of the masks' concatenated index space that falls in
+/// `slice`. The masks must all have different lengths.
+pub fn search_masks(cracker: &mut Cracker, masks: &[Mask], slice: Range<u64>) {
+    let resume = cracker.take_resume();
+    cracker.monitor().set_total(Some(slice.end - slice.start));
+    let mut sizer = BatchSizer::new(TARGET_BATCH_TIME, u64::MAX, cracker.threads());
+    let mut base = 0u64;
+
+    for mask in masks {
+        let total_combinations = mask.keyspace().expect("keyspace checked before the search");
+        let mask_base = base;
+        base += total_combinations;
+
+        let start = slice.start.max(mask_base);
+        let end = slice.end.min(base);
+        if start >= end {
+            continue;
+        }
+        let Some(resume_at) = resume_offset(resume, mask) else {
+            continue;
+        };
+        let range = (start - mask_base).max(resume_at)..end - mask_base;
+        if range.is_empty() {
+            continue;
+        }
+        cracker
+            .monitor()
+            .set_done(mask_base + range.start - slice.start);
+        if !search_mask(cracker, &mut sizer, mask, range) {
+            return;
+        }
+        if cracker.is_cancelled() {
+            return;
+        }
+    }
+}
+
+/// Offset to start `mask` from when resuming at `resume`, or `None` when
+/// its length was already completed, including when the checkpoint was
+/// written after its last batch.
+fn resume_offset(resume: Option<Position>, mask: &Mask) -> Option<u64> {
+    match resume {
+        Some(Position::Keyspace {
+            length: resume_length,
+            offset,
+        }) => match mask.len().cmp(&resume_length) {
+            std::cmp::Ordering::Less => None,
+            std::cmp::Ordering::Equal => {
+                Some(offset).filter(|&offset| mask.keyspace().is_some_and(|total| offset < total))
+            }
+            std::cmp::Ordering::Greater => Some(0),
+        },
+        _ => Some(0),
+    }
+}
+
+/// Enumerates the candidates of the mask with indices in `range`. Returns
+/// `false` once every target has been cracked or the job is cancelled.
+fn search_mask(
+    cracker: &mut Cracker,
+    sizer: &mut BatchSizer,
+    mask: &Mask,
+    range: Range<u64>,
+) -> bool {
+    let length = mask.len();
+    let total_combinations = mask.keyspace().expect("keyspace checked before the search");
+    cracker
+        .monitor()
+        .start_length(length, range.end - range.start);
+    cracker.emit(Event::LengthStarted {
+        length,
+        range: range.clone(),
+        combinations: total_combinations,
+    });
+
+    let mut offset = range.start;
+
+    while offset < range.end {
+        let current_batch = sizer.size().min(range.end - offset);
+        let batch_start = Instant::now();
+
+        let targets = cracker.targets();
+        let monitor = cracker.monitor();
+        let hits = cracker.install(|| {
+            (offset..offset + current_batch)
+                .into_par_iter()
+                .map_init(
+                    || ([0u8; MAX_CANDIDATE_LEN], monitor.counter()),
+                    |(buf, counter), idx| {
+                        let candidate = &mut buf[..length];
+                        mask.index_to_candidate(idx, candidate);
+                        counter.tick(candidate);
+                        cracker::hits(targets, candidate)
+                    },
+                )
+                .flatten_iter()
+                .collect()
+        });
+        sizer.record(current_batch, batch_start.elapsed());
+
+        if cracker.record(hits) {
+            return false;
+        }
+
+        offset += current_batch;
+        cracker.checkpoint(Position::Keyspace { length, offset });
+
+        let done = offset - range.start;
+        let total = range.end - range.start;
+        cracker.progress(done, Some(total), Some(done as f64 / total as f64));
+        cracker.wait_while_paused();
+        if cracker.is_cancelled() {
+            return false;
+        }
+    }
+
+    cracker.monitor().finish_length();
+    cracker.emit(Event::LengthComplete { length });
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resumes_at_the_checkpointed_offset() {
+        let mask = Mask::uniform(b"ab", 3);
+        let at = |length, offset| Some(Position::Keyspace { length, offset });
+        assert_eq!(resume_offset(None, &mask), Some(0));
+        assert_eq!(resume_offset(at(3, 5), &mask), Some(5));
+        assert_eq!(resume_offset(at(2, 4), &mask), Some(0));
+        assert_eq!(resume_offset(at(4, 0), &mask), None);
+        // A checkpoint after the last batch of the length.
+        assert_eq!(resume_offset(at(3, 8), &mask), None);
+    }
+}
@@ -0,0 +1,371 @@
+use clap::{Args, Parser, Subcommand};
+use hash_brute_force::{GenerateOptions, HashOptions, Node, Partition};
+use std::path::PathBuf;
+
+pub const DEFAULT_CHARSET: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+#[derive(Parser)]
+#[command(version, about = "Hash brute force")]
+pub struct Cli {
+    /// Number of worker threads (defaults to available cores)
+    #[arg(long, global = true)]
+    pub threads: Option<usize>,
+
+    /// Session file progress is checkpointed to, for `--restore`
+    #[arg(long, global = true)]
+    pub session: Option<PathBuf>,
+
+    /// Potfile of `hash:password` lines: hashes it already holds are
+    /// skipped and every password found is appended to it
+    #[arg(long, global = true)]
+    pub potfile: Option<PathBuf>,
+
+    /// File to append a JSON Lines stream of events to (start, progress,
+    /// found and how the search ended)
+    #[arg(long, global = true)]
+    pub json_events: Option<PathBuf>,
+
+    /// Seconds between status updates (0 disables them); pressing Enter
+    /// prints the status at any time
+    #[arg(long, global = true, default_value_t = 10)]
+    pub status_interval: u64,
+
+    /// Memory the checks running at once may use, e.g. `512M` or `8G`;
+    /// memory-hard hashes such as Argon2 run on fewer threads to stay
+    /// within it (defaults to half the available memory)
+    #[arg(long, global = true, value_parser = parse_size)]
+    pub memory_budget: Option<u64>,
+
+    /// Continue the search saved in the session file
+    #[arg(long, requires = "session")]
+    pub restore: bool,
+
+    /// Argon2 secret key (pepper) of the restored search, which session
+    /// files do not store
+    #[arg(long, requires = "restore")]
+    pub secret: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Enumerate every string over the charset up to the maximum length
+    Brute(BruteArgs),
+    /// Enumerate a mask with its own charset per position
+    Mask(MaskArgs),
+    /// Try every word of a wordlist
+    Wordlist(WordlistArgs),
+    /// Measure hashes per second of each algorithm
+    Bench(BenchArgs),
+    /// Hash inputs, to make targets and test vectors
+    Hash(HashArgs),
+    /// List the algorithms a hash could have been produced by
+    Identify {
+        /// Hash to identify
+        #[arg(long)]
+        hash: String,
+    },
+}
+
+#[derive(Args)]
+pub struct TargetArgs {
+    /// Target hash
+    #[arg(
+        long,
+        required_unless_present = "hash_file",
+        conflicts_with = "hash_file"
+    )]
+    pub hash: Option<String>,
+
+    /// File with one target hash per line
+    #[arg(long)]
+    pub hash_file: Option<PathBuf>,
+
+    /// Hash algorithm of the target (md5, sha1, sha256, sha512, bcrypt,
+    /// argon2, ...; see the README for all), detected
+    /// from the hash when omitted
+    #[arg(long)]
+    pub algo: Option<String>,
+
+    /// Parameters of raw Argon2 targets given as `<salt hex>:<output hex>`
+    /// instead of PHC strings, as `m=<KiB>,t=<iterations>,p=<lanes>`
+    #[arg(long, value_parser = parse_argon2_params)]
+    pub argon2_params: Option<(u32, u32, u32)>,
+
+    /// Argon2 variant of raw targets: argon2d, argon2i or argon2id
+    #[arg(long, default_value = "argon2id", requires = "argon2_params")]
+    pub variant: String,
+
+    /// Argon2 version of raw targets, 16 or 19
+    #[arg(long, default_value_t = 19, requires = "argon2_params")]
+    pub argon2_version: u32,
+
+    #[command(flatten)]
+    pub keys: Argon2KeyArgs,
+}
+
+/// Argon2 inputs that are never part of the hash.
+#[derive(Args)]
+pub struct Argon2KeyArgs {
+    /// Argon2 secret key (pepper)
+    #[arg(long)]
+    pub secret: Option<String>,
+
+    /// Argon2 associated data, at most 32 bytes
+    #[arg(long)]
+    pub associated_data: Option<String>,
+}
+
+impl Argon2KeyArgs {
+    pub fn options(&self) -> HashOptions {
+        HashOptions {
+            secret: self.secret.as_ref().map(|s| s.as_bytes().to_vec()),
+            associated_data: self.associated_data.as_ref().map(|s| s.as_bytes().to_vec()),
+            argon2: None,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct BruteArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+
+    /// Characters to build candidates from
+    #[arg(long, default_value = DEFAULT_CHARSET)]
+    pub charset: String,
+
+    /// Shortest candidate length
+    #[arg(long, default_value_t = 1)]
+    pub min_len: usize,
+
+    /// Longest candidate length
+    #[arg(long, default_value_t = 6)]
+    pub max_len: usize,
+
+    #[command(flatten)]
+    pub keyspace: KeyspaceArgs,
+}
+
+/// Restricts a search to a slice of the index space of all lengths.
+#[derive(Args)]
+pub struct KeyspaceArgs {
+    /// Skip the first N candidates
+    #[arg(long, default_value_t = 0)]
+    pub skip: u64,
+
+    /// Check at most N candidates after the skipped ones
+    #[arg(long)]
+    pub limit: Option<u64>,
+
+    /// Check only slice I of N equal slices, e.g. `2/4`
+    #[arg(long)]
+    pub node: Option<Node>,
+}
+
+impl KeyspaceArgs {
+    pub fn partition(&self) -> Partition {
+        Partition {
+            skip: self.skip,
+            limit: self.limit,
+            node: self.node,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct WordlistArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+
+    /// Wordlist file with one candidate per line, `-` for stdin
+    pub wordlist: PathBuf,
+
+    /// Hashcat rule file applied to every word
+    #[arg(long)]
+    pub rules: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct MaskArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+
+    /// Mask such as `?u?l?l?l?d?d`: `?l` lower, `?u` upper, `?d` digit,
+    /// `?h`/`?H` hex, `?s` special, `?a` all printable, `?b` any byte,
+    /// `?1`-`?4` custom charsets, anything else is a literal
+    pub mask: String,
+
+    /// Custom charset referenced as `?1`, e.g. `?l?d_`
+    #[arg(short = '1', long)]
+    pub custom_charset1: Option<String>,
+
+    /// Custom charset referenced as `?2`
+    #[arg(short = '2', long)]
+    pub custom_charset2: Option<String>,
+
+    /// Custom charset referenced as `?3`
+    #[arg(short = '3', long)]
+    pub custom_charset3: Option<String>,
+
+    /// Custom charset referenced as `?4`
+    #[arg(short = '4', long)]
+    pub custom_charset4: Option<String>,
+
+    /// Also try every shorter prefix of the mask, shortest first
+    #[arg(long)]
+    pub increment: bool,
+
+    #[command(flatten)]
+    pub keyspace: KeyspaceArgs,
+}
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Algorithms to benchmark
+    #[arg(long, value_delimiter = ',', default_value = "md5,sha1,bcrypt,argon2")]
+    pub algo: Vec<String>,
+
+    /// Thread counts to run each algorithm with (defaults to 1 and the
+    /// number of cores)
+    #[arg(long, value_delimiter = ',')]
+    pub thread_counts: Vec<usize>,
+
+    /// bcrypt costs to benchmark
+    #[arg(long, value_delimiter = ',', default_value = "10")]
+    pub bcrypt_cost: Vec<u32>,
+
+    /// Argon2id parameters to benchmark as `m=<KiB>,t=<iterations>,p=<lanes>`,
+    /// repeatable
+    #[arg(long, value_parser = parse_argon2_params, default_value = "m=19456,t=2,p=1")]
+    pub argon2: Vec<(u32, u32, u32)>,
+
+    /// Seconds to run each benchmark for
+    #[arg(long, default_value_t = 3.0)]
+    pub duration: f64,
+
+    /// Print the results as a JSON array instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args)]
+pub struct HashArgs {
+    /// Algorithm to hash with (md5, sha1, sha256, sha512, bcrypt, argon2, ...)
+    #[arg(long)]
+    pub algo: String,
+
+    /// Inputs to hash; read line by line from stdin when omitted
+    pub inputs: Vec<String>,
+
+    /// bcrypt cost
+    #[arg(long, default_value_t = bcrypt::DEFAULT_COST)]
+    pub cost: u32,
+
+    /// Variant: argon2d, argon2i or argon2id for Argon2 (default
+    /// argon2id), 2a, 2b, 2x or 2y for bcrypt (default 2b), sha1, sha256 or
+    /// sha512 for PBKDF2 (default sha256)
+    #[arg(long)]
+    pub variant: Option<String>,
+
+    /// Argon2 parameters as `m=<KiB>,t=<iterations>,p=<lanes>`
+    #[arg(long, value_parser = parse_argon2_params, default_value = "m=19456,t=2,p=1")]
+    pub argon2_params: (u32, u32, u32),
+
+    /// Argon2 version, 16 or 19
+    #[arg(long, default_value_t = 19)]
+    pub argon2_version: u32,
+
+    /// Salt: 8 to 48 bytes for Argon2, any bytes but `$` for PBKDF2, the
+    /// salt as written in the hash for md5crypt, SHA-crypt and yescrypt; a
+    /// random one per hash when omitted
+    #[arg(long)]
+    pub salt: Option<String>,
+
+    /// SHA-crypt rounds, written into the hash as `rounds=` (5000 when
+    /// omitted), or PBKDF2 iterations (600000 when omitted)
+    #[arg(long)]
+    pub rounds: Option<u32>,
+
+    /// Encoding of PBKDF2 hashes: django, passlib or phc
+    #[arg(long, default_value = "phc")]
+    pub pbkdf2_format: String,
+
+    /// yescrypt parameters in the notation of the hash
+    #[arg(long, default_value = "j9T")]
+    pub yescrypt_params: String,
+
+    /// Argon2 output length in bytes
+    #[arg(long, default_value_t = argon2::Params::DEFAULT_OUTPUT_LEN)]
+    pub output_len: usize,
+
+    /// Print Argon2 hashes raw as `<salt hex>:<output hex>` instead of PHC
+    /// strings
+    #[arg(long)]
+    pub raw: bool,
+
+    #[command(flatten)]
+    pub keys: Argon2KeyArgs,
+}
+
+impl HashArgs {
+    pub fn generate_options(&self) -> GenerateOptions {
+        GenerateOptions {
+            variant: self.variant.clone(),
+            cost: self.cost,
+            rounds: self.rounds,
+            salt: self.salt.clone(),
+            argon2_params: self.argon2_params,
+            argon2_version: self.argon2_version,
+            output_len: self.output_len,
+            raw: self.raw,
+            yescrypt_params: self.yescrypt_params.clone(),
+            pbkdf2_format: self.pbkdf2_format.clone(),
+            hash_options: self.keys.options(),
+        }
+    }
+}
+
+/// Parses a byte count with an optional `K`, `M`, `G` or `T` suffix, in
+/// powers of 1024.
+fn parse_size(s: &str) -> Result<u64, String> {
+    let upper = s.trim().to_ascii_uppercase();
+    let digits = upper.trim_end_matches(['B', 'I']);
+    let (number, shift) = match digits.char_indices().last() {
+        Some((i, 'K')) => (&digits[..i], 10),
+        Some((i, 'M')) => (&digits[..i], 20),
+        Some((i, 'G')) => (&digits[..i], 30),
+        Some((i, 'T')) => (&digits[..i], 40),
+        _ => (digits, 0),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+        .ok_or_else(|| format!("Expected a size such as 512M or 8G, got '{}'", s))
+}
+
+fn parse_argon2_params(s: &str) -> Result<(u32, u32, u32), String> {
+    let (mut m, mut t, mut p) = (None, None, None);
+    for param in s.split(',') {
+        let (key, value) = param
+            .split_once('=')
+            .ok_or_else(|| format!("Expected key=value, got '{}'", param))?;
+        let value: u32 = value
+            .parse()
+            .map_err(|_| format!("Invalid value for {}: '{}'", key, value))?;
+        match key {
+            "m" => m = Some(value),
+            "t" => t = Some(value),
+            "p" => p = Some(value),
+            _ => return Err(format!("Unknown Argon2 parameter '{}'", key)),
+        }
+    }
+    match (m, t, p) {
+        (Some(m), Some(t), Some(p)) => Ok((m, t, p)),
+        _ => Err("Expected m=<KiB>,t=<iterations>,p=<lanes>".to_string()),
+    }
+}
@@ -0,0 +1,262 @@
+use crate::engine::{CancelToken, CandidateSource, Crack, Event, Outcome, Progress, Status};
+use crate::keyspace::Partition;
+use crate::monitor::Monitor;
+use crate::potfile;
+use crate::session::{Position, Session, SessionFile};
+use crate::targets::TargetSet;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// State of a running job shared by every candidate source: the targets
+/// left, the passwords found so far, checkpointing and event reporting.
+pub(crate) struct Cracker<'a> {
+    targets: TargetSet,
+    total_targets: usize,
+    found: Vec<Crack>,
+    start: Instant,
+    session: Option<SessionFile>,
+    potfile: Option<PathBuf>,
+    resume: Option<Position>,
+    monitor: Monitor,
+    /// Pool of the job's own the batches run on when the job may not use
+    /// every thread of the current one, or keeps working memory on them.
+    pool: Option<ThreadPool>,
+    cancel: &'a CancelToken,
+    events: &'a mut dyn FnMut(Event),
+}
+
+impl<'a> Cracker<'a> {
+    pub fn new(
+        targets: TargetSet,
+        total_targets: usize,
+        found: Vec<Crack>,
+        monitor: Monitor,
+        cancel: &'a CancelToken,
+        events: &'a mut dyn FnMut(Event),
+    ) -> Self {
+        monitor.start(total_targets, found.len());
+        Cracker {
+            targets,
+            total_targets,
+            found,
+            start: Instant::now(),
+            session: None,
+            potfile: None,
+            resume: None,
+            monitor,
+            pool: None,
+            cancel,
+            events,
+        }
+    }
+
+    /// Runs the batches on `threads` threads when that is fewer than the
+    /// current pool has. With `own_pool` the job starts threads of its own
+    /// whatever their number: memory-hard checks keep their working memory
+    /// on the threads they run on, which then end with the job.
+    pub fn limit_threads(&mut self, threads: usize, own_pool: bool) -> Result<(), String> {
+        if own_pool || threads < rayon::current_num_threads() {
+            let pool = ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .map_err(|e| format!("Failed to start {} threads: {}", threads, e))?;
+            self.pool = Some(pool);
+        }
+        Ok(())
+    }
+
+    /// Number of threads the batches run on.
+    pub fn threads(&self) -> usize {
+        self.pool
+            .as_ref()
+            .map_or_else(rayon::current_num_threads, ThreadPool::current_num_threads)
+    }
+
+    /// Runs `op`, a batch, on the threads of the job.
+    pub fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match &self.pool {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
+
+    pub fn resume_from(&mut self, position: Option<Position>) {
+        self.resume = position;
+    }
+
+    /// Checkpoints progress to `path` while the search runs, or only when it
+    /// stops without `periodic`.
+    pub fn save_session(
+        &mut self,
+        path: PathBuf,
+        source: CandidateSource,
+        partition: Partition,
+        periodic: bool,
+    ) {
+        self.session = Some(SessionFile::new(path, source, partition, periodic));
+    }
+
+    /// Appends every password found to the potfile at `path`.
+    pub fn append_to_potfile(&mut self, path: PathBuf) {
+        self.potfile = Some(path);
+    }
+
+    /// Where a restored search left off. The mode running the search takes
+    /// it once, before its first batch.
+    pub fn take_resume(&mut self) -> Option<Position> {
+        self.resume.take()
+    }
+
+    /// Records that everything before `position` has been checked, writing
+    /// the session file when a checkpoint is due or the job is stopping or
+    /// pausing.
+    pub fn checkpoint(&mut self, position: Position) {
+        let Some(file) = &mut self.session else {
+            return;
+        };
+        if !file.is_due() && !self.cancel.is_cancelled() && !self.cancel.is_paused() {
+            return;
+        }
+        let session = Session {
+            algo: self.targets.algorithm().name().to_string(),
+            options: self.targets.options().clone(),
+            uses_secret: self.targets.options().secret.is_some(),
+            hashes: self.targets.hashes(),
+            found: self.found.clone(),
+            total_targets: self.total_targets,
+            source: file.source.clone(),
+            partition: file.partition,
+            position: Some(position),
+        };
+        if let Err(e) = file.save(&session) {
+            (self.events)(Event::Warning(e));
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub fn targets(&self) -> &TargetSet {
+        &self.targets
+    }
+
+    pub fn monitor(&self) -> &Monitor {
+        &self.monitor
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Blocks at a batch boundary while the job is paused.
+    pub fn wait_while_paused(&mut self) {
+        if !self.cancel.is_paused() {
+            return;
+        }
+        self.emit(Event::Paused);
+        self.cancel.wait_while_paused();
+        self.emit(Event::Resumed);
+    }
+
+    pub fn emit(&mut self, event: Event) {
+        (self.events)(event);
+    }
+
+    pub fn warn(&mut self, message: String) {
+        self.emit(Event::Warning(message));
+    }
+
+    pub fn progress(&mut self, done: u64, total: Option<u64>, fraction: Option<f64>) {
+        let elapsed = self.elapsed();
+        self.emit(Event::Progress(Progress {
+            done,
+            total,
+            fraction,
+            elapsed,
+        }));
+    }
+
+    /// Reports the hits of a batch and drops the cracked hashes. Returns
+    /// `true` once every target has been cracked.
+    pub fn record(&mut self, hits: Vec<Crack>) -> bool {
+        if hits.is_empty() {
+            return false;
+        }
+
+        let mut cracked = HashSet::new();
+        let new = self.found.len();
+        for crack in hits {
+            if !cracked.insert(crack.hash.clone()) {
+                continue;
+            }
+            (self.events)(Event::Found(&crack));
+            self.found.push(crack);
+        }
+        self.targets.remove(&cracked);
+        self.monitor.set_cracked(self.found.len());
+
+        if let Some(path) = &self.potfile
+            && let Err(e) = potfile::append(path, &self.found[new..])
+        {
+            (self.events)(Event::Warning(e));
+        }
+        self.is_done()
+    }
+
+    pub fn finish(self) -> Outcome {
+        let status = if self.is_done() {
+            Status::AllCracked
+        } else if self.cancel.is_cancelled() {
+            Status::Cancelled
+        } else {
+            Status::Exhausted
+        };
+
+        // A finished search has nothing left to restore.
+        if status != Status::Cancelled
+            && let Some(file) = &self.session
+            && file.is_owned()
+        {
+            let _ = fs::remove_file(&file.path);
+        }
+
+        Outcome {
+            found: self.found,
+            total_targets: self.total_targets,
+            status,
+            elapsed: self.start.elapsed(),
+        }
+    }
+}
+
+/// Returns a [`Crack`] for every remaining target `candidate` is the
+/// password of. Called from the worker threads.
+pub(crate) fn hits(targets: &TargetSet, candidate: &[u8]) -> Vec<Crack> {
+    targets
+        .matches(candidate)
+        .into_iter()
+        .map(|hash| Crack {
+            hash: hash.to_string(),
+            password: display_candidate(candidate),
+        })
+        .collect()
+}
+
+/// Formats a candidate for output, hex-encoding it in hashcat's `$HEX[...]`
+/// notation when it would not survive a line of the potfile as is: when it
+/// is not valid UTF-8, holds a control character such as `\n` or `\r`, or
+/// itself starts with `$HEX[`.
+pub fn display_candidate(candidate: &[u8]) -> String {
+    match std::str::from_utf8(candidate) {
+        Ok(s) if !s.bytes().any(|b| b < 0x20) && !s.starts_with("$HEX[") => s.to_string(),
+        _ => format!("$HEX[{}]", hex::encode(candidate)),
+    }
+}
@@ -0,0 +1,552 @@
+//! The glibc crypt(3) schemes of `/etc/shadow`: md5crypt (`$1$`) and the
+//! SHA-crypt pair sha256crypt (`$5$`) and sha512crypt (`$6$`).
+
+use crate::algorithm::{GenerateOptions, HashAlgorithm, HashOptions, Target};
+use crate::detect;
+use digest::Digest;
+
+/// The crypt(3) base64 alphabet.
+pub(crate) const ITOA64: &[u8; 64] =
+    b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// md5crypt's fixed number of rounds.
+const MD5_ROUNDS: u32 = 1000;
+const MD5_SALT_LEN: usize = 8;
+
+/// SHA-crypt's rounds without a `rounds=` parameter, and the range others
+/// are clamped to.
+const DEFAULT_ROUNDS: u32 = 5000;
+const MIN_ROUNDS: u32 = 1000;
+const MAX_ROUNDS: u32 = 999_999_999;
+const SHA_SALT_LEN: usize = 16;
+
+/// Bytes of the final digest, most significant first, that make up each
+/// group of characters; a group of n bytes is written as n + 1 characters.
+const MD5_ORDER: &[&[usize]] = &[
+    &[0, 6, 12],
+    &[1, 7, 13],
+    &[2, 8, 14],
+    &[3, 9, 15],
+    &[4, 10, 5],
+    &[11],
+];
+
+const SHA256_ORDER: &[&[usize]] = &[
+    &[0, 10, 20],
+    &[21, 1, 11],
+    &[12, 22, 2],
+    &[3, 13, 23],
+    &[24, 4, 14],
+    &[15, 25, 5],
+    &[6, 16, 26],
+    &[27, 7, 17],
+    &[18, 28, 8],
+    &[9, 19, 29],
+    &[31, 30],
+];
+
+const SHA512_ORDER: &[&[usize]] = &[
+    &[0, 21, 42],
+    &[22, 43, 1],
+    &[44, 2, 23],
+    &[3, 24, 45],
+    &[25, 46, 4],
+    &[47, 5, 26],
+    &[6, 27, 48],
+    &[28, 49, 7],
+    &[50, 8, 29],
+    &[9, 30, 51],
+    &[31, 52, 10],
+    &[53, 11, 32],
+    &[12, 33, 54],
+    &[34, 55, 13],
+    &[56, 14, 35],
+    &[15, 36, 57],
+    &[37, 58, 16],
+    &[59, 17, 38],
+    &[18, 39, 60],
+    &[40, 61, 19],
+    &[62, 20, 41],
+    &[63],
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Scheme {
+    Md5,
+    Sha256,
+    Sha512,
+}
+
+impl Scheme {
+    fn order(self) -> &'static [&'static [usize]] {
+        match self {
+            Scheme::Md5 => MD5_ORDER,
+            Scheme::Sha256 => SHA256_ORDER,
+            Scheme::Sha512 => SHA512_ORDER,
+        }
+    }
+
+    /// Characters of the encoded digest.
+    fn encoded_len(self) -> usize {
+        self.order().iter().map(|group| group.len() + 1).sum()
+    }
+
+    /// Longest salt the scheme uses, in characters.
+    fn max_salt(self) -> usize {
+        match self {
+            Scheme::Md5 => MD5_SALT_LEN,
+            Scheme::Sha256 | Scheme::Sha512 => SHA_SALT_LEN,
+        }
+    }
+
+    /// Rounds of hashes without a `rounds=` parameter.
+    fn default_rounds(self) -> u32 {
+        match self {
+            Scheme::Md5 => MD5_ROUNDS,
+            Scheme::Sha256 | Scheme::Sha512 => DEFAULT_ROUNDS,
+        }
+    }
+}
+
+/// One of the crypt(3) schemes, told apart by the id of its hashes.
+pub struct CryptAlgorithm {
+    name: &'static str,
+    id: &'static str,
+    scheme: Scheme,
+}
+
+pub static MD5_CRYPT: CryptAlgorithm = CryptAlgorithm {
+    name: "md5crypt",
+    id: "1",
+    scheme: Scheme::Md5,
+};
+
+pub static SHA256_CRYPT: CryptAlgorithm = CryptAlgorithm {
+    name: "sha256crypt",
+    id: "5",
+    scheme: Scheme::Sha256,
+};
+
+pub static SHA512_CRYPT: CryptAlgorithm = CryptAlgorithm {
+    name: "sha512crypt",
+    id: "6",
+    scheme: Scheme::Sha512,
+};
+
+/// A `$id$[rounds=N$]salt$digest` hash, split once. The digest is kept
+/// encoded and compared as crypt(3) callers compare the whole string.
+struct CryptTarget {
+    scheme: Scheme,
+    rounds: u32,
+    salt: Vec<u8>,
+    expected: String,
+}
+
+impl CryptAlgorithm {
+    fn parse(&self, hash: &str) -> Result<CryptTarget, String> {
+        let invalid = |reason: String| format!("Invalid {} hash: {}", self.name, reason);
+        let rest = hash
+            .strip_prefix('$')
+            .and_then(|rest| rest.strip_prefix(self.id))
+            .and_then(|rest| rest.strip_prefix('$'))
+            .ok_or_else(|| invalid(format!("expected the ${}$ prefix", self.id)))?;
+        let (rounds, rest) = match rest.strip_prefix("rounds=") {
+            Some(_) if self.scheme == Scheme::Md5 => {
+                return Err(invalid("md5crypt takes no rounds parameter".to_string()));
+            }
+            Some(rest) => {
+                let (digits, rest) = rest
+                    .split_once('$')
+                    .ok_or_else(|| invalid("expected rounds=<N>$<salt>$<digest>".to_string()))?;
+                let rounds = digits
+                    .parse::<u32>()
+                    .map_err(|_| invalid(format!("rounds '{}' is not a number", digits)))?;
+                (rounds.clamp(MIN_ROUNDS, MAX_ROUNDS), rest)
+            }
+            None => (self.scheme.default_rounds(), rest),
+        };
+        let (salt, digest) = rest
+            .split_once('$')
+            .ok_or_else(|| invalid("expected <salt>$<digest> after the prefix".to_string()))?;
+
+        let max_salt = self.scheme.max_salt();
+        if salt.len() > max_salt {
+            return Err(invalid(format!(
+                "salt is {} characters, at most {} are used",
+                salt.len(),
+                max_salt
+            )));
+        }
+        let len = self.scheme.encoded_len();
+        if digest.len() != len || !digest.bytes().all(|b| ITOA64.contains(&b)) {
+            return Err(invalid(format!(
+                "digest must be {} characters of crypt base64",
+                len
+            )));
+        }
+
+        Ok(CryptTarget {
+            scheme: self.scheme,
+            rounds,
+            salt: salt.as_bytes().to_vec(),
+            expected: digest.to_string(),
+        })
+    }
+}
+
+/// md5crypt as in FreeBSD and glibc.
+fn md5_crypt(password: &[u8], salt: &[u8]) -> Vec<u8> {
+    let alternate = md5::Md5::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(password)
+        .finalize();
+    let mut ctx = md5::Md5::new()
+        .chain_update(password)
+        .chain_update(b"$1$")
+        .chain_update(salt);
+    add_repeated(&mut ctx, &alternate, password.len());
+    let mut n = password.len();
+    while n > 0 {
+        // A NUL byte for each set bit of the length, the password's first
+        // byte for each clear one.
+        if n & 1 != 0 {
+            ctx.update([0]);
+        } else {
+            ctx.update(&password[..1]);
+        }
+        n >>= 1;
+    }
+    let mut digest = ctx.finalize();
+
+    for i in 0..MD5_ROUNDS {
+        let mut ctx = md5::Md5::new();
+        if i & 1 != 0 {
+            ctx.update(password);
+        } else {
+            ctx.update(digest);
+        }
+        if i % 3 != 0 {
+            ctx.update(salt);
+        }
+        if i % 7 != 0 {
+            ctx.update(password);
+        }
+        if i & 1 != 0 {
+            ctx.update(digest);
+        } else {
+            ctx.update(password);
+        }
+        digest = ctx.finalize();
+    }
+    digest.to_vec()
+}
+
+/// SHA-crypt as specified by Ulrich Drepper, with `D` SHA-256 or SHA-512.
+fn sha_crypt<D: Digest>(password: &[u8], salt: &[u8], rounds: u32) -> Vec<u8> {
+    let alternate = D::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(password)
+        .finalize();
+    let mut ctx = D::new().chain_update(password).chain_update(salt);
+    add_repeated(&mut ctx, &alternate, password.len());
+    let mut n = password.len();
+    while n > 0 {
+        if n & 1 != 0 {
+            ctx.update(&alternate);
+        } else {
+            ctx.update(password);
+        }
+        n >>= 1;
+    }
+    let mut digest = ctx.finalize();
+
+    let mut ctx = D::new();
+    for _ in 0..password.len() {
+        ctx.update(password);
+    }
+    let p_bytes: Vec<u8> = ctx
+        .finalize()
+        .iter()
+        .copied()
+        .cycle()
+        .take(password.len())
+        .collect();
+    let mut ctx = D::new();
+    for _ in 0..16 + digest[0] as usize {
+        ctx.update(salt);
+    }
+    let s_bytes = ctx.finalize()[..salt.len()].to_vec();
+
+    for i in 0..rounds {
+        let mut ctx = D::new();
+        if i & 1 != 0 {
+            ctx.update(&p_bytes);
+        } else {
+            ctx.update(&digest);
+        }
+        if i % 3 != 0 {
+            ctx.update(&s_bytes);
+        }
+        if i % 7 != 0 {
+            ctx.update(&p_bytes);
+        }
+        if i & 1 != 0 {
+            ctx.update(&digest);
+        } else {
+            ctx.update(&p_bytes);
+        }
+        digest = ctx.finalize();
+    }
+    digest.to_vec()
+}
+
+/// Adds `len` bytes of `block` repeated.
+fn add_repeated<D: Digest>(ctx: &mut D, block: &[u8], len: usize) {
+    let mut left = len;
+    while left > block.len() {
+        ctx.update(block);
+        left -= block.len();
+    }
+    ctx.update(&block[..left]);
+}
+
+/// Appends the low `chars` groups of 6 bits of `value` in crypt base64,
+/// least significant first.
+pub(crate) fn push_base64(out: &mut String, mut value: u32, chars: usize) {
+    for _ in 0..chars {
+        out.push(ITOA64[(value & 0x3f) as usize] as char);
+        value >>= 6;
+    }
+}
+
+fn encode(digest: &[u8], order: &[&[usize]]) -> String {
+    let mut out = String::with_capacity(digest.len() * 4 / 3 + 1);
+    for group in order {
+        let value = group
+            .iter()
+            .fold(0u32, |value, &i| value << 8 | digest[i] as u32);
+        push_base64(&mut out, value, group.len() + 1);
+    }
+    out
+}
+
+impl CryptTarget {
+    fn hash(&self, password: &[u8]) -> String {
+        let digest = match self.scheme {
+            Scheme::Md5 => md5_crypt(password, &self.salt),
+            Scheme::Sha256 => sha_crypt::<sha2::Sha256>(password, &self.salt, self.rounds),
+            Scheme::Sha512 => sha_crypt::<sha2::Sha512>(password, &self.salt, self.rounds),
+        };
+        encode(&digest, self.scheme.order())
+    }
+}
+
+/// A salt of `len` random characters of the crypt(3) alphabet.
+pub(crate) fn random_salt(len: usize) -> Result<String, String> {
+    let mut bytes = vec![0u8; len];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate a salt: {}", e))?;
+    Ok(bytes
+        .iter()
+        .map(|&b| ITOA64[(b & 0x3f) as usize] as char)
+        .collect())
+}
+
+/// Hashes `password` into a hash of `algo`. `salt` is written into the hash
+/// as is, random when omitted; `rounds`, for SHA-crypt only, is written as
+/// a `rounds=` parameter.
+pub fn hash_crypt(
+    password: &[u8],
+    algo: &CryptAlgorithm,
+    rounds: Option<u32>,
+    salt: Option<&str>,
+) -> Result<String, String> {
+    let max_salt = algo.scheme.max_salt();
+    let salt = match salt {
+        Some(salt) if salt.len() > max_salt || !salt.bytes().all(|b| ITOA64.contains(&b)) => {
+            return Err(format!(
+                "{} salt must be at most {} characters of ./0-9A-Za-z",
+                algo.name, max_salt
+            ));
+        }
+        Some(salt) => salt.to_string(),
+        None => random_salt(max_salt)?,
+    };
+    let prefix = match rounds {
+        None => format!("${}${}$", algo.id, salt),
+        Some(_) if algo.scheme == Scheme::Md5 => {
+            return Err("md5crypt always runs 1000 rounds".to_string());
+        }
+        Some(rounds) if !(MIN_ROUNDS..=MAX_ROUNDS).contains(&rounds) => {
+            return Err(format!(
+                "{} rounds must be in {}..={}",
+                algo.name, MIN_ROUNDS, MAX_ROUNDS
+            ));
+        }
+        Some(rounds) => format!("${}$rounds={}${}$", algo.id, rounds, salt),
+    };
+    let target = CryptTarget {
+        scheme: algo.scheme,
+        rounds: rounds.unwrap_or(algo.scheme.default_rounds()),
+        salt: salt.into_bytes(),
+        expected: String::new(),
+    };
+    Ok(prefix + &target.hash(password))
+}
+
+/// Checks `password` against a md5crypt, sha256crypt or sha512crypt hash.
+pub fn verify_crypt(password: &[u8], hash: &str) -> bool {
+    [&MD5_CRYPT, &SHA256_CRYPT, &SHA512_CRYPT]
+        .into_iter()
+        .find(|algo| algo.identify(hash))
+        .and_then(|algo| algo.parse(hash).ok())
+        .is_some_and(|target| target.check(password))
+}
+
+impl HashAlgorithm for CryptAlgorithm {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn identify(&self, hash: &str) -> bool {
+        detect::crypt_id(hash) == Some(self.id) && self.parse(hash).is_ok()
+    }
+
+    fn prepare(&self, hash: &str, _options: &HashOptions) -> Result<Box<dyn Target>, String> {
+        Ok(Box::new(self.parse(hash)?))
+    }
+
+    fn generation_error(&self) -> Option<String> {
+        None
+    }
+
+    fn generate(&self, password: &[u8], options: &GenerateOptions) -> Result<String, String> {
+        hash_crypt(password, self, options.rounds, options.salt.as_deref())
+    }
+}
+
+impl Target for CryptTarget {
+    fn check(&self, candidate: &[u8]) -> bool {
+        self.hash(candidate) == self.expected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hashes of "password" by libxcrypt.
+    const VECTORS: &[(&CryptAlgorithm, &str)] = &[
+        (&MD5_CRYPT, "$1$saltsalt$qjXMvbEw8oaL.CzflDtaK/"),
+        (
+            &SHA256_CRYPT,
+            "$5$abc$6va2Z4O.keO7R84v9g0B9d2NkB7NOyTy0sMeQ5Z3LLA",
+        ),
+        (
+            &SHA256_CRYPT,
+            "$5$rounds=1000$abc$chB2229SaEAMndXolPyqp1RFge2UaeCAJVGEAvqr4M3",
+        ),
+        (
+            &SHA512_CRYPT,
+            "$6$rounds=1234$saltsalt$1iW69TlJIAtqS7HlJiDqaqr1Q1481k61I..kUMpz7qn2fjzWCNr.qGBWQxjAOFNQIIgWkTZUrZKTPx4ZOLNTv1",
+        ),
+        (
+            &SHA512_CRYPT,
+            "$6$saltsaltsaltsalt$bcXJ8qxwY5sQ4v8MTl.0B1jeZ0z0JlA9jjmbUoCJZ.1wYXiLTU.q2ILyrDJLm890lyfuF7sWAeli0yjOyFPkf0",
+        ),
+    ];
+
+    #[test]
+    fn verifies_known_hashes() {
+        for &(algo, hash) in VECTORS {
+            assert!(algo.identify(hash), "{}", hash);
+            assert!(verify_crypt(b"password", hash), "{}", hash);
+            assert!(!verify_crypt(b"Password", hash), "{}", hash);
+        }
+    }
+
+    #[test]
+    fn hashes_empty_passwords() {
+        assert!(verify_crypt(b"", "$1$saltsalt$5Jhcit4zN9UlGiA0txPkO0"));
+        assert!(verify_crypt(
+            b"",
+            "$6$saltsalt$qkTgsCrWMTAS9gBGcf9W60sFfH.hU0oTCAOJjhbz5tSp/sU3/xXZK4OFwCtq8lIIdpJ6CatVdOTSHKp97TPkt/"
+        ));
+    }
+
+    #[test]
+    fn generates_known_hashes() {
+        for &(algo, hash) in VECTORS {
+            let target = algo.parse(hash).unwrap();
+            let rounds = hash.contains("rounds=").then_some(target.rounds);
+            let salt = std::str::from_utf8(&target.salt).unwrap();
+            assert_eq!(
+                hash_crypt(b"password", algo, rounds, Some(salt)).unwrap(),
+                hash
+            );
+        }
+        let random = hash_crypt(b"password", &SHA512_CRYPT, None, None).unwrap();
+        assert!(verify_crypt(b"password", &random), "{}", random);
+    }
+
+    #[test]
+    fn clamps_rounds() {
+        let below = "$5$rounds=999$abc$chB2229SaEAMndXolPyqp1RFge2UaeCAJVGEAvqr4M3";
+        assert_eq!(SHA256_CRYPT.parse(below).unwrap().rounds, MIN_ROUNDS);
+        assert!(verify_crypt(b"password", below));
+        let above = "$5$rounds=1000000000$abc$chB2229SaEAMndXolPyqp1RFge2UaeCAJVGEAvqr4M3";
+        assert_eq!(SHA256_CRYPT.parse(above).unwrap().rounds, MAX_ROUNDS);
+        assert!(hash_crypt(b"password", &SHA256_CRYPT, Some(999), None).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_hashes() {
+        let cases = [
+            (
+                &MD5_CRYPT,
+                "$1$saltsalts$qjXMvbEw8oaL.CzflDtaK/",
+                "salt is 9",
+            ),
+            (
+                &SHA256_CRYPT,
+                "$5$abcdefghijklmnopq$6va2Z4O.keO7R84v9g0B9d2NkB7NOyTy0sMeQ5Z3LLA",
+                "salt is 17",
+            ),
+            (
+                &MD5_CRYPT,
+                "$1$rounds=1000$saltsalt$qjXMvbEw8oaL.CzflDtaK/",
+                "no rounds",
+            ),
+            (
+                &SHA256_CRYPT,
+                "$5$rounds=x$abc$6va2Z4O.keO7R84v9g0B9d2NkB7NOyTy0sMeQ5Z3LLA",
+                "not a number",
+            ),
+            (
+                &SHA256_CRYPT,
+                "$5$abc$6va2Z4O.keO7R84v9g0B9d2NkB7NOyTy0sMeQ5Z3LL",
+                "43 characters",
+            ),
+            (
+                &SHA256_CRYPT,
+                "$5$abc$6va2Z4O.keO7R84v9g0B9d2NkB7NOyTy0sMeQ5Z3LL!",
+                "43 characters",
+            ),
+            (
+                &SHA512_CRYPT,
+                "$5$abc$6va2Z4O.keO7R84v9g0B9d2NkB7NOyTy0sMeQ5Z3LLA",
+                "prefix",
+            ),
+        ];
+        for (algo, hash, reason) in cases {
+            match algo.parse(hash) {
+                Ok(_) => panic!("{} parsed", hash),
+                Err(e) => assert!(e.contains(reason), "{}: {}", hash, e),
+            }
+            assert!(!algo.identify(hash), "{}", hash);
+        }
+        assert!(hash_crypt(b"x", &MD5_CRYPT, None, Some("saltsalts")).is_err());
+        assert!(hash_crypt(b"x", &MD5_CRYPT, Some(1000), None).is_err());
+    }
+}
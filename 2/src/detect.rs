@@ -0,0 +1,34 @@
+use crate::algorithm::{ALGORITHMS, HashAlgorithm};
+
+/// Returns every registered algorithm that recognizes the shape of `hash`.
+pub fn detect(hash: &str) -> Vec<&'static dyn HashAlgorithm> {
+    let hash = hash.trim();
+    ALGORITHMS
+        .iter()
+        .copied()
+        .filter(|algo| algo.identify(hash))
+        .collect()
+}
+
+/// Algorithms whose hashes look like `hash` but that detection leaves to
+/// another, see [`HashAlgorithm::matches_shape`].
+pub fn lookalikes(hash: &str) -> Vec<&'static dyn HashAlgorithm> {
+    let hash = hash.trim();
+    ALGORITHMS
+        .iter()
+        .copied()
+        .filter(|algo| !algo.identify(hash) && algo.matches_shape(hash))
+        .collect()
+}
+
+/// Identifier of a PHC string or modular-crypt hash: `argon2id` for
+/// `$argon2id$v=19$...`, `2b` for `$2b$10$...`.
+pub fn crypt_id(hash: &str) -> Option<&str> {
+    let rest = hash.strip_prefix('$')?;
+    let (id, _) = rest.split_once('$')?;
+    if id.is_empty() { None } else { Some(id) }
+}
+
+pub fn is_hex(hash: &str, len: usize) -> bool {
+    hash.len() == len && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
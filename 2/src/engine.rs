@@ -0,0 +1,515 @@
+//! The cracking engine: a [`Job`] checks the candidates of a
+//! [`CandidateSource`] against a [`TargetSet`] and reports what happens
+//! through [`Event`]s.
+
+use crate::brute::{self, MAX_CANDIDATE_LEN};
+use crate::cracker::Cracker;
+use crate::keyspace::{self, Partition};
+use crate::mask::Mask;
+use crate::monitor::Monitor;
+use crate::rules::RuleSet;
+use crate::session::{Position, Session};
+use crate::targets::TargetSet;
+use crate::{algorithm, potfile, wordlist};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// Where the candidates of a job come from.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum CandidateSource {
+    /// Every string over `charset` with a length in `min_len..=max_len`.
+    Brute {
+        charset: String,
+        min_len: usize,
+        max_len: usize,
+    },
+    /// A hashcat-style mask, see [`Mask::parse`]; with `increment` every
+    /// prefix of the mask is tried first, shortest to longest.
+    Mask {
+        mask: String,
+        custom_charsets: [Option<String>; 4],
+        increment: bool,
+    },
+    /// Every line of a wordlist (`-` for stdin), mangled by every rule of
+    /// the hashcat rule file when one is given.
+    Wordlist {
+        path: PathBuf,
+        rules: Option<PathBuf>,
+    },
+}
+
+/// A cracked hash.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Crack {
+    pub hash: String,
+    /// The password, in hashcat's `$HEX[...]` notation when it is not
+    /// valid UTF-8 or holds control characters.
+    pub password: String,
+}
+
+/// Something that happened while a job ran.
+pub enum Event<'a> {
+    /// Enumeration of the candidates of one length started, over `range` of
+    /// its `combinations` indices.
+    LengthStarted {
+        length: usize,
+        range: Range<u64>,
+        combinations: u64,
+    },
+    LengthComplete {
+        length: usize,
+    },
+    /// Reading the wordlist started, after `resumed_words` words already
+    /// checked before a restore.
+    WordlistStarted {
+        path: &'a Path,
+        rules: Option<usize>,
+        resumed_words: u64,
+    },
+    WordlistComplete {
+        words: u64,
+    },
+    /// A batch of candidates has been checked.
+    Progress(Progress),
+    /// The job paused at a batch boundary, see [`CancelToken::pause`].
+    Paused,
+    Resumed,
+    Found(&'a Crack),
+    /// A problem that does not stop the job, such as a rule that could not
+    /// be parsed or a session file that could not be written.
+    Warning(String),
+}
+
+/// Progress through the current length or wordlist.
+#[derive(Clone, Copy)]
+pub struct Progress {
+    /// Candidates, or words of a wordlist, checked so far.
+    pub done: u64,
+    /// Candidates to check in total, when known.
+    pub total: Option<u64>,
+    /// Fraction done, when known. For wordlists it is measured in bytes.
+    pub fraction: Option<f64>,
+    pub elapsed: Duration,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    AllCracked,
+    Exhausted,
+    Cancelled,
+}
+
+/// Result of a finished job.
+pub struct Outcome {
+    /// Every hash cracked, including before a restore.
+    pub found: Vec<Crack>,
+    pub total_targets: usize,
+    pub status: Status,
+    pub elapsed: Duration,
+}
+
+/// Stops, pauses and resumes a running job at the next batch boundary.
+/// Clones share the state, so a job can be controlled from another thread.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<Control>);
+
+#[derive(Default)]
+struct Control {
+    cancelled: AtomicBool,
+    paused: Mutex<bool>,
+    changed: Condvar,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Relaxed);
+        // Wakes a paused job so it can stop.
+        let _paused = self.0.paused.lock().unwrap();
+        self.0.changed.notify_all();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn pause(&self) {
+        *self.0.paused.lock().unwrap() = true;
+    }
+
+    pub fn resume(&self) {
+        *self.0.paused.lock().unwrap() = false;
+        self.0.changed.notify_all();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.0.paused.lock().unwrap()
+    }
+
+    /// Blocks until the job is resumed or cancelled.
+    pub(crate) fn wait_while_paused(&self) {
+        let mut paused = self.0.paused.lock().unwrap();
+        while *paused && !self.is_cancelled() {
+            paused = self.0.changed.wait(paused).unwrap();
+        }
+    }
+}
+
+/// The candidate source, checked and expanded before the job runs.
+pub(crate) enum Plan {
+    Keyspace {
+        masks: Vec<Mask>,
+        total: u64,
+        slice: Range<u64>,
+    },
+    Wordlist {
+        path: PathBuf,
+        rules: Option<RuleSet>,
+    },
+}
+
+/// A search for the passwords of a set of targets.
+pub struct Job {
+    targets: TargetSet,
+    total_targets: usize,
+    found: Vec<Crack>,
+    source: CandidateSource,
+    partition: Partition,
+    plan: Plan,
+    session: Option<PathBuf>,
+    checkpoint_periodically: bool,
+    potfile: Option<PathBuf>,
+    resume: Option<Position>,
+    memory_budget: Option<u64>,
+    monitor: Monitor,
+}
+
+impl Job {
+    /// Checks the candidate source, failing on invalid lengths, masks or
+    /// rule files and on keyspaces that do not fit in 64 bits.
+    pub fn new(targets: TargetSet, source: CandidateSource) -> Result<Job, String> {
+        let plan = plan(&source, &Partition::default())?;
+        Ok(Job {
+            total_targets: targets.len(),
+            targets,
+            found: Vec::new(),
+            source,
+            partition: Partition::default(),
+            plan,
+            session: None,
+            checkpoint_periodically: false,
+            potfile: None,
+            resume: None,
+            memory_budget: None,
+            monitor: Monitor::new(),
+        })
+    }
+
+    /// Restricts a brute force or mask job to a slice of its keyspace.
+    pub fn with_partition(mut self, partition: Partition) -> Result<Job, String> {
+        self.plan = plan(&self.source, &partition)?;
+        self.partition = partition;
+        Ok(self)
+    }
+
+    /// Checkpoints progress to `path` while the job runs, for [`Job::restore`].
+    pub fn with_session(mut self, path: PathBuf) -> Job {
+        self.session = Some(path);
+        self.checkpoint_periodically = true;
+        self
+    }
+
+    /// Like [`Job::with_session`], but writes the session file only when the
+    /// job is paused or cancelled.
+    pub fn with_stop_checkpoint(mut self, path: PathBuf) -> Job {
+        self.session = Some(path);
+        self.checkpoint_periodically = false;
+        self
+    }
+
+    /// Caps the candidates checked at once so their working memory stays
+    /// within `bytes`; only memory-hard algorithms such as Argon2 need any.
+    /// At least one thread runs whatever the budget.
+    pub fn with_memory_budget(mut self, bytes: u64) -> Job {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    /// Number of threads the job runs on: those of the current rayon pool,
+    /// fewer when the memory budget does not allow for one check on each.
+    pub fn threads(&self) -> usize {
+        let threads = rayon::current_num_threads();
+        match (self.memory_budget, self.targets.memory_per_check()) {
+            (Some(budget), per_check) if per_check > 0 => {
+                threads.min((budget / per_check).max(1) as usize)
+            }
+            _ => threads,
+        }
+    }
+
+    /// The file progress is checkpointed to, if any.
+    pub fn session(&self) -> Option<&Path> {
+        self.session.as_deref()
+    }
+
+    /// Takes the hashes the potfile at `path` already holds a password for
+    /// out of the search, and appends every password found to it.
+    pub fn with_potfile(mut self, path: PathBuf) -> Result<Job, String> {
+        let cracked = potfile::read(&path, &self.targets.hashes())?;
+        let hashes: HashSet<String> = cracked.iter().map(|crack| crack.hash.clone()).collect();
+        self.targets.remove(&hashes);
+        self.found.extend(cracked);
+        self.potfile = Some(path);
+        Ok(self)
+    }
+
+    /// Continues the job saved in a session file, checkpointing to the same
+    /// file. Session files do not store the Argon2 secret: a job that used
+    /// one needs it passed again as `secret`.
+    pub fn restore(path: &Path, secret: Option<Vec<u8>>) -> Result<Job, String> {
+        let mut session = Session::load(path)?;
+        let algo = algorithm::find(&session.algo)
+            .ok_or_else(|| format!("Unknown algorithm '{}' in session", session.algo))?;
+        match (session.uses_secret, secret.is_some()) {
+            (true, false) => {
+                return Err(format!(
+                    "Session {} used a secret, which is not saved: pass --secret again",
+                    path.display()
+                ));
+            }
+            (false, true) => {
+                return Err(format!("Session {} used no secret", path.display()));
+            }
+            _ => session.options.secret = secret,
+        }
+
+        let mut targets = TargetSet::with_options(algo, session.options);
+        for hash in &session.hashes {
+            targets
+                .insert(hash)
+                .map_err(|e| format!("Invalid hash in session: {}", e))?;
+        }
+
+        let mut job = Job::new(targets, session.source)?
+            .with_partition(session.partition)?
+            .with_session(path.to_path_buf());
+        job.total_targets = session.total_targets;
+        job.found = session.found;
+        job.resume = session.position;
+        Ok(job)
+    }
+
+    pub fn source(&self) -> &CandidateSource {
+        &self.source
+    }
+
+    /// Hashes not cracked yet.
+    pub fn targets(&self) -> &TargetSet {
+        &self.targets
+    }
+
+    /// Hashes cracked before the job runs, by a restored session or found
+    /// in the potfile.
+    pub fn found(&self) -> &[Crack] {
+        &self.found
+    }
+
+    /// Number of hashes the job started with, before any restore.
+    pub fn total_targets(&self) -> usize {
+        self.total_targets
+    }
+
+    /// For brute force and mask jobs, the slice of the keyspace searched
+    /// and the size of the whole keyspace.
+    pub fn keyspace(&self) -> Option<(Range<u64>, u64)> {
+        match &self.plan {
+            Plan::Keyspace { total, slice, .. } => Some((slice.clone(), *total)),
+            Plan::Wordlist { .. } => None,
+        }
+    }
+
+    /// Handle to the live counters of the job, to sample from another
+    /// thread while it runs.
+    pub fn monitor(&self) -> Monitor {
+        self.monitor.clone()
+    }
+
+    /// Runs the job on the current rayon thread pool, or on [`Job::threads`]
+    /// threads of its own when fewer or the checks are memory-hard, until
+    /// every target is cracked, the candidates run out or `cancel` is
+    /// triggered.
+    pub fn run(
+        self,
+        cancel: &CancelToken,
+        mut on_event: impl FnMut(Event),
+    ) -> Result<Outcome, String> {
+        let threads = self.threads();
+        let memory_hard = self.targets.memory_per_check() > 0;
+        let Job {
+            targets,
+            total_targets,
+            found,
+            source,
+            partition,
+            plan,
+            session,
+            checkpoint_periodically,
+            potfile,
+            resume,
+            memory_budget: _,
+            monitor,
+        } = self;
+
+        let mut cracker = Cracker::new(
+            targets,
+            total_targets,
+            found,
+            monitor,
+            cancel,
+            &mut on_event,
+        );
+        cracker.limit_threads(threads, memory_hard)?;
+        cracker.resume_from(resume);
+        if let Some(path) = session {
+            cracker.save_session(path, source, partition, checkpoint_periodically);
+        }
+        if let Some(path) = potfile {
+            cracker.append_to_potfile(path);
+        }
+        if cracker.is_done() {
+            return Ok(cracker.finish());
+        }
+
+        match &plan {
+            Plan::Keyspace { masks, slice, .. } => {
+                brute::search_masks(&mut cracker, masks, slice.clone());
+            }
+            Plan::Wordlist { path, rules } => {
+                if let Some(rules) = rules {
+                    for warning in rules.skipped() {
+                        cracker.warn(warning.clone());
+                    }
+                }
+                wordlist::wordlist_attack(&mut cracker, path, rules.as_ref())
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            }
+        }
+        Ok(cracker.finish())
+    }
+}
+
+fn plan(source: &CandidateSource, partition: &Partition) -> Result<Plan, String> {
+    let masks = match source {
+        CandidateSource::Brute {
+            charset,
+            min_len,
+            max_len,
+        } => {
+            if charset.is_empty() || !charset.is_ascii() {
+                return Err("Charset must be a non-empty ASCII string".to_string());
+            }
+            if *min_len == 0 || min_len > max_len || *max_len > MAX_CANDIDATE_LEN {
+                return Err(format!(
+                    "Length range must satisfy 1 <= min-len <= max-len <= {}",
+                    MAX_CANDIDATE_LEN
+                ));
+            }
+            brute::brute_masks(charset.as_bytes(), *min_len..=*max_len)
+        }
+        CandidateSource::Mask {
+            mask,
+            custom_charsets,
+            increment,
+        } => brute::increment_masks(&Mask::parse(mask, custom_charsets)?, *increment),
+        CandidateSource::Wordlist { path, rules } => {
+            if *partition != Partition::default() {
+                return Err(
+                    "Keyspace partitioning applies to brute force and masks only".to_string(),
+                );
+            }
+            let rules = rules.as_deref().map(RuleSet::load).transpose()?;
+            return Ok(Plan::Wordlist {
+                path: path.clone(),
+                rules,
+            });
+        }
+    };
+
+    let total = keyspace::total(&masks).ok_or("Keyspace does not fit in 64 bits")?;
+    let slice = keyspace::slice(total, partition)?;
+    Ok(Plan::Keyspace {
+        masks,
+        total,
+        slice,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::argon2_hash::Argon2;
+    use crate::md5_hash::MD5;
+    use rayon::ThreadPoolBuilder;
+    use std::fs;
+
+    /// A job on `hash`, with `budget` bytes of memory if any.
+    fn job(algo: &'static dyn algorithm::HashAlgorithm, hash: &str, budget: Option<u64>) -> Job {
+        let mut targets = TargetSet::new(algo);
+        targets.insert(hash).unwrap();
+        let source = CandidateSource::Brute {
+            charset: "abc".to_string(),
+            min_len: 1,
+            max_len: 2,
+        };
+        let job = Job::new(targets, source).unwrap();
+        match budget {
+            Some(bytes) => job.with_memory_budget(bytes),
+            None => job,
+        }
+    }
+
+    #[test]
+    fn restores_with_the_secret_given_again() {
+        let path = std::env::temp_dir().join(format!("restore-test-{}", std::process::id()));
+        let with_secret = crate::session::tests::session(Some(b"pepper")).save(&path);
+        let missing = Job::restore(&path, None).map(|_| ());
+        let given = Job::restore(&path, Some(b"pepper".to_vec()));
+        let without_secret = crate::session::tests::session(None).save(&path);
+        let unexpected = Job::restore(&path, Some(b"pepper".to_vec())).map(|_| ());
+        fs::remove_file(&path).unwrap();
+
+        with_secret.unwrap();
+        without_secret.unwrap();
+        assert!(missing.unwrap_err().contains("--secret"));
+        let job = given.unwrap();
+        assert_eq!(
+            job.targets().options().secret.as_deref(),
+            Some(&b"pepper"[..])
+        );
+        assert!(unexpected.is_err());
+    }
+
+    #[test]
+    fn fits_threads_to_the_memory_budget() {
+        // 64 MiB a check.
+        let argon2 = "$argon2id$v=19$m=65536,t=2,p=1$c29tZXNhbHQ$CTFhFdXPJO1aFaMaO6Mm5c8y7cJHAph8ArZWb2GRPPc";
+        let md5 = "5f4dcc3b5aa765d61d8327deb882cf99";
+        let pool = ThreadPoolBuilder::new().num_threads(8).build().unwrap();
+        pool.install(|| {
+            assert_eq!(job(&Argon2, argon2, None).threads(), 8);
+            assert_eq!(job(&Argon2, argon2, Some(1 << 30)).threads(), 8);
+            assert_eq!(job(&Argon2, argon2, Some(256 << 20)).threads(), 4);
+            assert_eq!(job(&Argon2, argon2, Some((256 << 20) - 1)).threads(), 3);
+            assert_eq!(job(&Argon2, argon2, Some(1)).threads(), 1);
+            assert_eq!(job(&MD5, md5, Some(1)).threads(), 8);
+        });
+    }
+}
@@ -0,0 +1,156 @@
+//! Splitting the global index space, every mask's candidates concatenated
+//! in enumeration order, into disjoint slices for separate machines.
+
+use crate::mask::Mask;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Slice `index` of `count` equal slices, written `i/N` with `i` from 1.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Node {
+    pub index: u64,
+    pub count: u64,
+}
+
+impl FromStr for Node {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, count) = s
+            .split_once('/')
+            .ok_or_else(|| format!("Expected i/N, got '{}'", s))?;
+        let index: u64 = index
+            .parse()
+            .map_err(|_| format!("Invalid node index '{}'", index))?;
+        let count: u64 = count
+            .parse()
+            .map_err(|_| format!("Invalid node count '{}'", count))?;
+        if index == 0 || index > count {
+            return Err(format!("Node index must be between 1 and {}", count));
+        }
+        Ok(Node { index, count })
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+/// Which part of the global index space a run covers.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Partition {
+    /// Candidates skipped at the start.
+    pub skip: u64,
+    /// Candidates checked at most after the skipped ones.
+    pub limit: Option<u64>,
+    /// Slice of what `skip` and `limit` leave.
+    pub node: Option<Node>,
+}
+
+/// Size of the global index space of `masks`, if it fits in 64 bits.
+pub fn total(masks: &[Mask]) -> Option<u64> {
+    masks
+        .iter()
+        .try_fold(0u64, |acc, mask| acc.checked_add(mask.keyspace()?))
+}
+
+/// The part of `0..total` a partition covers: `skip`/`limit` first, then
+/// the node's slice of what is left. Nodes given the same partition and
+/// every index from 1 to N cover the range exactly once.
+pub fn slice(total: u64, args: &Partition) -> Result<Range<u64>, String> {
+    if args.skip > total {
+        return Err(format!(
+            "Skip {} is past the end of the keyspace ({})",
+            args.skip, total
+        ));
+    }
+    let end = args
+        .limit
+        .map_or(total, |limit| args.skip.saturating_add(limit).min(total));
+
+    let Some(node) = args.node else {
+        return Ok(args.skip..end);
+    };
+    let len = (end - args.skip) as u128;
+    let bound = |i: u64| args.skip + (len * i as u128 / node.count as u128) as u64;
+    Ok(bound(node.index - 1)..bound(node.index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The slices of every node from 1 to `count` of a partition.
+    fn slices(total: u64, skip: u64, limit: Option<u64>, count: u64) -> Vec<Range<u64>> {
+        (1..=count)
+            .map(|index| {
+                let partition = Partition {
+                    skip,
+                    limit,
+                    node: Some(Node { index, count }),
+                };
+                slice(total, &partition).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nodes_cover_the_range_exactly_once() {
+        for (total, skip, limit, count) in [
+            (100, 0, None, 1),
+            (100, 0, None, 3),
+            (100, 7, None, 4),
+            (100, 7, Some(50), 6),
+            // Limit past the end.
+            (100, 90, Some(50), 3),
+            (100, 0, Some(u64::MAX), 7),
+            // Nothing left after the skip.
+            (100, 100, None, 3),
+            // More nodes than candidates: some get none.
+            (5, 0, None, 8),
+            (5, 2, Some(2), 5),
+            (u64::MAX, 1, None, 3),
+        ] {
+            let expected = skip..limit.map_or(total, |limit| skip.saturating_add(limit).min(total));
+            let whole = Partition {
+                skip,
+                limit,
+                node: None,
+            };
+            assert_eq!(slice(total, &whole).unwrap(), expected);
+
+            let slices = slices(total, skip, limit, count);
+            assert_eq!(slices.first().unwrap().start, expected.start);
+            assert_eq!(slices.last().unwrap().end, expected.end);
+            for pair in slices.windows(2) {
+                assert_eq!(pair[0].end, pair[1].start, "{:?}", slices);
+            }
+            let sizes: Vec<u64> = slices.iter().map(|s| s.end - s.start).collect();
+            let (min, max) = (sizes.iter().min().unwrap(), sizes.iter().max().unwrap());
+            assert!(max - min <= 1, "{:?}", sizes);
+        }
+    }
+
+    #[test]
+    fn rejects_a_skip_past_the_end() {
+        let partition = Partition {
+            skip: 101,
+            ..Partition::default()
+        };
+        assert!(slice(100, &partition).is_err());
+    }
+
+    #[test]
+    fn parses_nodes() {
+        let node: Node = "2/5".parse().unwrap();
+        assert_eq!((node.index, node.count), (2, 5));
+        assert_eq!(node.to_string(), "2/5");
+        for invalid in ["0/5", "6/5", "1/0", "2", "a/5", "1/b"] {
+            assert!(invalid.parse::<Node>().is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Hash brute force engine.
+//!
+//! Build a [`TargetSet`] for a [`HashAlgorithm`], describe where candidates
+//! come from with a [`CandidateSource`] and run a [`Job`]:
+//!
+//! ```no_run
+//! use hash_brute_force::{CancelToken, CandidateSource, Event, Job, TargetSet, algorithm};
+//!
+//! let mut targets = TargetSet::new(algorithm::find("md5").unwrap());
+//! targets.insert("900150983cd24fb0d6963f7d28e17f72").unwrap();
+//!
+//! let source = CandidateSource::Brute {
+//!     charset: "abc".to_string(),
+//!     min_len: 1,
+//!     max_len: 3,
+//! };
+//! let outcome = Job::new(targets, source)
+//!     .unwrap()
+//!     .run(&CancelToken::new(), |event| {
+//!         if let Event::Found(crack) = event {
+//!             println!("{}:{}", crack.hash, crack.password);
+//!         }
+//!     })
+//!     .unwrap();
+//! assert_eq!(outcome.found.len(), 1);
+//! ```
+
+pub mod algorithm;
+pub mod argon2_hash;
+mod batch;
+pub mod bcrypt_hash;
+pub mod bench;
+pub mod blake_hash;
+mod brute;
+mod cracker;
+pub mod crypt_hash;
+pub mod detect;
+pub mod engine;
+pub mod keyspace;
+pub mod mask;
+pub mod md5_hash;
+pub mod monitor;
+pub mod ntlm_hash;
+pub mod pbkdf2_hash;
+pub mod potfile;
+pub mod rules;
+pub mod session;
+pub mod sha1_hash;
+pub mod sha2_hash;
+pub mod sha3_hash;
+pub mod targets;
+mod wordlist;
+pub mod yescrypt_hash;
+
+pub use algorithm::{GenerateOptions, HashAlgorithm, HashOptions};
+pub use brute::MAX_CANDIDATE_LEN;
+pub use engine::{CancelToken, CandidateSource, Crack, Event, Job, Outcome, Progress, Status};
+pub use keyspace::{Node, Partition};
+pub use monitor::{LengthStats, Monitor, Snapshot};
+pub use targets::TargetSet;
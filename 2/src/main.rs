@@ -1,92 +1,528 @@
-mod argon2_hash;
-mod bcrypt_hash;
-mod md5_hash;
-mod sha1_hash;
-
-use rayon::prelude::*;
-use std::time::Instant;
-
-const TARGET_HASH: &str =
-    "$argon2id$v=19$m=65536,t=3,p=2$c2FsdHNhbHQ$PUF5UxxoUY++mMekkQwFurL0ZsTtB7lelO23zcyZQ0c";
-const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
-// const CHARSET: &[u8] = b"0123456789";
-
-fn hash_string(input: &str) -> bool {
-    // sha1_hash::hash_sha1(input) == TARGET_HASH
-    // md5_hash::hash_md5(input) == TARGET_HASH
-    // bcrypt_hash::verify_bcrypt(input, TARGET_HASH)
-    argon2_hash::verify_argon2(input, TARGET_HASH)
+mod cli;
+mod output;
+mod status;
+
+use clap::{CommandFactory, Parser};
+use cli::{BenchArgs, Cli, Command, HashArgs, TargetArgs};
+use hash_brute_force::argon2_hash::{Argon2Params, Variant};
+use hash_brute_force::bench::{self, BenchAlgorithm, BenchResult};
+use hash_brute_force::{
+    CancelToken, CandidateSource, HashAlgorithm, Job, Partition, Status, TargetSet, algorithm,
+    detect,
+};
+use output::{EventLog, Printer};
+use status::StatusReporter;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
+
+/// Session file an interrupted search is saved to when `--session` is not
+/// given.
+const DEFAULT_SESSION: &str = "hash_brute_force.session";
+
+fn load_targets(args: &TargetArgs) -> TargetSet {
+    let hashes: Vec<String> = match (&args.hash, &args.hash_file) {
+        (Some(hash), _) => vec![hash.trim().to_string()],
+        (None, Some(path)) => match fs::read_to_string(path) {
+            Ok(contents) => contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", path.display(), e);
+                process::exit(2);
+            }
+        },
+        (None, None) => unreachable!("clap requires --hash or --hash-file"),
+    };
+    let Some(first) = hashes.first() else {
+        eprintln!("No hashes to crack");
+        process::exit(2);
+    };
+
+    let mut options = args.keys.options();
+    if let Some((m_cost, t_cost, p_cost)) = args.argon2_params {
+        if !args
+            .algo
+            .as_deref()
+            .is_some_and(|algo| algo.eq_ignore_ascii_case("argon2"))
+        {
+            eprintln!("--argon2-params describes raw Argon2 hashes and requires --algo argon2");
+            process::exit(2);
+        }
+        options.argon2 = Some(Argon2Params {
+            variant: parse_variant(&args.variant),
+            version: args.argon2_version,
+            m_cost,
+            t_cost,
+            p_cost,
+        });
+    }
+
+    let algo = select_algorithm(args.algo.as_deref(), first);
+    let mut targets = TargetSet::with_options(algo, options);
+    for (i, hash) in hashes.iter().enumerate() {
+        if let Err(e) = targets.insert(hash) {
+            if hashes.len() == 1 {
+                eprintln!("{}", e);
+                process::exit(2);
+            }
+            eprintln!("Skipping hash on line {}: {}", i + 1, e);
+        }
+    }
+    if targets.is_empty() {
+        eprintln!("No valid {} hashes to crack", algo.name());
+        process::exit(2);
+    }
+
+    match &args.hash {
+        Some(hash) => println!("Target hash: {}", hash),
+        None => println!("Target hashes: {}", targets.len()),
+    }
+    targets
 }
 
-fn index_to_string(mut index: u64, length: usize) -> String {
-    let mut result = vec![0u8; length];
-    for i in (0..length).rev() {
-        result[i] = CHARSET[(index % CHARSET.len() as u64) as usize];
-        index /= CHARSET.len() as u64;
+fn select_algorithm(name: Option<&str>, hash: &str) -> &'static dyn HashAlgorithm {
+    if let Some(name) = name {
+        return algorithm::find(name).unwrap_or_else(|| {
+            eprintln!(
+                "Unknown algorithm '{}', expected one of: {}",
+                name,
+                algorithm::names().join(", ")
+            );
+            process::exit(2);
+        });
+    }
+
+    match detect::detect(hash).as_slice() {
+        [] => {
+            eprintln!("Could not detect the hash type, pass --algo explicitly");
+            process::exit(2);
+        }
+        [algo] => {
+            println!("Detected algorithm: {}", algo.name());
+            *algo
+        }
+        candidates => {
+            let names: Vec<_> = candidates.iter().map(|algo| algo.name()).collect();
+            eprintln!(
+                "Ambiguous hash type, pass --algo with one of: {}",
+                names.join(", ")
+            );
+            process::exit(2);
+        }
     }
-    String::from_utf8(result).unwrap()
 }
 
-fn cpu_brute_force(target: &str, max_len: usize) -> Option<String> {
-    println!("Target hash: {}", target);
-    println!("Using {} threads\n", rayon::current_num_threads());
+fn build_job(command: Command, session: Option<PathBuf>) -> Job {
+    let (target_args, source, partition) = match command {
+        Command::Brute(args) => (
+            args.target,
+            CandidateSource::Brute {
+                charset: args.charset,
+                min_len: args.min_len,
+                max_len: args.max_len,
+            },
+            args.keyspace.partition(),
+        ),
+        Command::Mask(args) => (
+            args.target,
+            CandidateSource::Mask {
+                mask: args.mask,
+                custom_charsets: [
+                    args.custom_charset1,
+                    args.custom_charset2,
+                    args.custom_charset3,
+                    args.custom_charset4,
+                ],
+                increment: args.increment,
+            },
+            args.keyspace.partition(),
+        ),
+        Command::Wordlist(args) => (
+            args.target,
+            CandidateSource::Wordlist {
+                path: args.wordlist,
+                rules: args.rules,
+            },
+            Partition::default(),
+        ),
+        Command::Bench(_) | Command::Hash(_) | Command::Identify { .. } => {
+            unreachable!("only search commands run a job")
+        }
+    };
 
-    let start = Instant::now();
+    let targets = load_targets(&target_args);
+    let job = Job::new(targets, source)
+        .and_then(|job| job.with_partition(partition))
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(2);
+        });
+    match session {
+        Some(path) => job.with_session(path),
+        None => job.with_stop_checkpoint(PathBuf::from(DEFAULT_SESSION)),
+    }
+}
 
-    for length in 1..=max_len {
-        let total_combinations = (CHARSET.len() as u64).pow(length as u32);
-        println!(
-            "Trying length {}: {} combinations",
-            length, total_combinations
-        );
+fn restore_job(path: &Path, secret: Option<&str>) -> Job {
+    let secret = secret.map(|s| s.as_bytes().to_vec());
+    let job = Job::restore(path, secret).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+    println!(
+        "Restoring session {}: {}/{} {} hashes left",
+        path.display(),
+        job.targets().len(),
+        job.total_targets(),
+        job.targets().algorithm().name()
+    );
+    job
+}
+
+fn run_job(mut job: Job, cli: &Cli) {
+    if let Some(path) = cli.potfile.clone() {
+        job = job.with_potfile(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(2);
+        });
+        for crack in job.found() {
+            println!("✅ {}: {} (already cracked)", crack.hash, crack.password);
+        }
+    }
+    let mut events = cli.json_events.as_deref().map(|path| {
+        EventLog::open(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(2);
+        })
+    });
+
+    if let Some((slice, total)) = job.keyspace()
+        && slice != (0..total)
+    {
+        println!("Keyspace slice {}..{} of {}", slice.start, slice.end, total);
+    }
+    if let Some(budget) = cli
+        .memory_budget
+        .or_else(|| available_memory().map(|m| m / 2))
+    {
+        job = job.with_memory_budget(budget);
+        let per_check = job.targets().memory_per_check();
+        if per_check > budget {
+            eprintln!(
+                "Warning: one check needs {} of memory, more than the {} budget",
+                format_bytes(per_check),
+                format_bytes(budget)
+            );
+        }
+        if job.threads() < rayon::current_num_threads() {
+            println!(
+                "Memory budget {} allows {} checks of {} at once",
+                format_bytes(budget),
+                job.threads(),
+                format_bytes(per_check)
+            );
+        }
+    }
+    println!("Using {} threads\n", job.threads());
+
+    if let Some(events) = &mut events {
+        events.start(&job);
+    }
+    let interval = (cli.status_interval > 0).then(|| Duration::from_secs(cli.status_interval));
+    // Keys are read from stdin, unless it is the wordlist itself.
+    let keys = io::stdin().is_terminal()
+        && !matches!(job.source(), CandidateSource::Wordlist { path, .. } if path == Path::new("-"));
+    let control = CancelToken::new();
+    let reporter = StatusReporter::start(job.monitor(), interval, keys.then(|| control.clone()));
+    handle_interrupt(&control);
 
-        let batch_size = 10_000_000u64;
-        let mut offset = 0u64;
-
-        while offset < total_combinations {
-            let current_batch = batch_size.min(total_combinations - offset);
-
-            let result = (offset..offset + current_batch)
-                .into_par_iter()
-                .find_map_any(|idx| {
-                    let candidate = index_to_string(idx, length);
-                    let result = hash_string(&candidate);
-                    if result { Some(candidate) } else { None }
-                });
-
-            if let Some(password) = result {
-                let elapsed = start.elapsed();
-                println!("\n✅ SUCCESS! Password found: {}", password);
-                println!("Time elapsed: {:.2?}", elapsed);
-                return Some(password);
+    let session = job.session().map(Path::to_path_buf);
+    let mut printer = Printer::new(job.total_targets() == 1);
+    let outcome = job
+        .run(&control, |event| {
+            printer.on_event(&event);
+            if let Some(events) = &mut events {
+                events.on_event(&event);
             }
+        })
+        .unwrap_or_else(|e| {
+            status::restore_terminal();
+            eprintln!("{}", e);
+            process::exit(2);
+        });
+    reporter.stop();
+    if let Some(events) = &mut events {
+        events.finish(&outcome);
+    }
+
+    match outcome.status {
+        Status::AllCracked if outcome.total_targets > 1 => {
+            println!("\nAll {} hashes cracked", outcome.total_targets);
+        }
+        Status::AllCracked => {}
+        Status::Exhausted if outcome.total_targets == 1 => {
+            println!("\nPassword not found in search space");
+        }
+        Status::Exhausted => println!(
+            "\nSearch space exhausted: {}/{} hashes cracked",
+            outcome.found.len(),
+            outcome.total_targets
+        ),
+        Status::Cancelled => {
+            println!(
+                "\nStopped: {}/{} hashes cracked",
+                outcome.found.len(),
+                outcome.total_targets
+            );
+            if let Some(path) = session.filter(|path| path.exists()) {
+                println!(
+                    "Progress saved, continue with: --session {} --restore",
+                    path.display()
+                );
+            }
+        }
+    }
+    println!("Time elapsed: {:.2?}", outcome.elapsed);
+
+    if outcome.found.is_empty() {
+        process::exit(1);
+    }
+}
+
+/// Memory available to new processes, where the system reports it.
+fn available_memory() -> Option<u64> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))?;
+    let kib: u64 = line.trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kib * 1024)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    if unit == "B" {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, unit)
+    }
+}
 
-            offset += current_batch;
+/// The first Ctrl-C stops the job at the end of its batch, writing a
+/// checkpoint; a second one exits at once.
+fn handle_interrupt(control: &CancelToken) {
+    let control = control.clone();
+    let result = ctrlc::set_handler(move || {
+        if control.is_cancelled() {
+            status::restore_terminal();
+            process::exit(130);
+        }
+        eprintln!("\nStopping after the current batch, press Ctrl-C again to abort");
+        control.cancel();
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to install the Ctrl-C handler: {}", e);
+    }
+}
 
-            if offset % (batch_size * 10) == 0 {
+fn run_bench(args: &BenchArgs) {
+    let mut algorithms = Vec::new();
+    for name in &args.algo {
+        match name.to_ascii_lowercase().as_str() {
+            "bcrypt" => algorithms.extend(
+                args.bcrypt_cost
+                    .iter()
+                    .map(|&cost| BenchAlgorithm::Bcrypt { cost }),
+            ),
+            "argon2" => algorithms.extend(args.argon2.iter().map(|&(m_cost, t_cost, p_cost)| {
+                BenchAlgorithm::Argon2 {
+                    m_cost,
+                    t_cost,
+                    p_cost,
+                }
+            })),
+            _ => match algorithm::find(name) {
+                Some(algo) => algorithms.push(BenchAlgorithm::Unsalted(algo)),
+                None => {
+                    eprintln!(
+                        "Unknown algorithm '{}', expected one of: {}",
+                        name,
+                        algorithm::names().join(", ")
+                    );
+                    process::exit(2);
+                }
+            },
+        }
+    }
+
+    let mut thread_counts = args.thread_counts.clone();
+    if thread_counts.is_empty() {
+        thread_counts.push(1);
+        thread_counts.push(std::thread::available_parallelism().map_or(1, |n| n.get()));
+        thread_counts.dedup();
+    }
+    if thread_counts.contains(&0) {
+        eprintln!("Thread counts must be at least 1");
+        process::exit(2);
+    }
+    let duration = Duration::try_from_secs_f64(args.duration)
+        .ok()
+        .filter(|d| !d.is_zero())
+        .unwrap_or_else(|| {
+            eprintln!("Duration must be a positive number of seconds");
+            process::exit(2);
+        });
+
+    if !args.json {
+        println!(
+            "{:<10} {:<20} {:>7} {:>14}",
+            "Algorithm", "Params", "Threads", "Hashes/s"
+        );
+    }
+    let mut results: Vec<BenchResult> = Vec::new();
+    for &algorithm in &algorithms {
+        for &threads in &thread_counts {
+            let result = bench::run(algorithm, threads, duration).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(2);
+            });
+            if !args.json {
                 println!(
-                    "  Progress: {}/{} ({:.1}%) - {:.2?}",
-                    offset,
-                    total_combinations,
-                    (offset as f64 / total_combinations as f64) * 100.0,
-                    start.elapsed()
+                    "{:<10} {:<20} {:>7} {:>14.1}",
+                    result.algorithm, result.params, result.threads, result.rate
                 );
             }
+            results.push(result);
+        }
+    }
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&results).unwrap());
+    }
+}
+
+fn parse_variant(variant: &str) -> Variant {
+    variant.parse().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    })
+}
+
+/// Why the `hash` subcommand cannot produce hashes of `algo`, if it cannot.
+fn run_hash(args: &HashArgs) {
+    let Some(algo) = algorithm::find(&args.algo) else {
+        eprintln!(
+            "Unknown algorithm '{}', expected one of: {}",
+            args.algo,
+            algorithm::names().join(", ")
+        );
+        process::exit(2);
+    };
+    if let Some(e) = algo.generation_error() {
+        eprintln!("{}", e);
+        process::exit(2);
+    }
+    let options = args.generate_options();
+    let print_hash = |input: &[u8]| match algo.generate(input, &options) {
+        Ok(hash) => println!("{}", hash),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
         }
+    };
 
-        println!("  Length {} complete - {:.2?}", length, start.elapsed());
+    if !args.inputs.is_empty() {
+        for input in &args.inputs {
+            print_hash(input.as_bytes());
+        }
+        return;
+    }
+    let mut stdin = io::stdin().lock();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match stdin.read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Failed to read stdin: {}", e);
+                process::exit(2);
+            }
+        }
+        while matches!(line.last(), Some(b'\n' | b'\r')) {
+            line.pop();
+        }
+        print_hash(&line);
     }
+}
 
-    println!("\nPassword not found in search space");
-    println!("Time elapsed: {:.2?}", start.elapsed());
-    None
+fn identify(hash: &str) {
+    let candidates = detect::detect(hash);
+    let lookalikes = detect::lookalikes(hash);
+    if candidates.is_empty() && lookalikes.is_empty() {
+        println!("Unknown hash type");
+        process::exit(1);
+    }
+    for algo in candidates {
+        println!("{}", algo.name());
+    }
+    for algo in lookalikes {
+        println!("{} (same shape, select with --algo)", algo.name());
+    }
 }
 
 fn main() {
+    let mut cli = Cli::parse();
+
+    let threads = cli
+        .threads
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
     rayon::ThreadPoolBuilder::new()
-        .num_threads(16)
+        .num_threads(threads)
         .build_global()
         .unwrap();
 
-    cpu_brute_force(TARGET_HASH, 6);
+    let job = match (cli.restore, cli.command.take()) {
+        (true, None) => {
+            let path = cli.session.as_deref().expect("clap requires --session");
+            restore_job(path, cli.secret.as_deref())
+        }
+        (true, Some(_)) => {
+            eprintln!("--restore takes the command from the session file, not the command line");
+            process::exit(2);
+        }
+        (false, Some(Command::Bench(args))) => {
+            run_bench(&args);
+            return;
+        }
+        (false, Some(Command::Hash(args))) => {
+            run_hash(&args);
+            return;
+        }
+        (false, Some(Command::Identify { hash })) => {
+            identify(&hash);
+            return;
+        }
+        (false, Some(command)) => build_job(command, cli.session.clone()),
+        (false, None) => {
+            Cli::command().print_help().unwrap();
+            process::exit(2);
+        }
+    };
+
+    run_job(job, &cli);
 }
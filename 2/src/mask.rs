@@ -0,0 +1,129 @@
+//! Masks with a charset per position, in hashcat's syntax: `?u?l?l?l?d?d`.
+
+use crate::brute::MAX_CANDIDATE_LEN;
+
+const LOWER: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &[u8] = b"0123456789";
+const SPECIAL: &[u8] = b" !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+pub struct Mask {
+    positions: Vec<Vec<u8>>,
+}
+
+impl Mask {
+    /// Parses a mask, where `?1`..`?4` refer to `custom` charsets.
+    pub fn parse(mask: &str, custom: &[Option<String>; 4]) -> Result<Mask, String> {
+        let mut custom_sets: [Option<Vec<u8>>; 4] = Default::default();
+        for (i, set) in custom.iter().enumerate() {
+            if let Some(set) = set {
+                let expanded = expand_charset(set.as_bytes(), &Default::default())
+                    .map_err(|e| format!("Custom charset {}: {}", i + 1, e))?;
+                custom_sets[i] = Some(expanded);
+            }
+        }
+
+        let mut positions = Vec::new();
+        let mut bytes = mask.bytes();
+        while let Some(b) = bytes.next() {
+            if b != b'?' {
+                positions.push(vec![b]);
+                continue;
+            }
+            let class = bytes.next().ok_or("Mask ends with a lone '?'")?;
+            positions.push(charset_class(class, &custom_sets)?);
+        }
+
+        if positions.is_empty() {
+            return Err("Empty mask".to_string());
+        }
+        if positions.len() > MAX_CANDIDATE_LEN {
+            return Err(format!(
+                "Mask is longer than {} positions",
+                MAX_CANDIDATE_LEN
+            ));
+        }
+        Ok(Mask { positions })
+    }
+
+    /// Mask applying the same charset to each of `length` positions, as the
+    /// plain brute force does.
+    pub fn uniform(charset: &[u8], length: usize) -> Mask {
+        Mask {
+            positions: vec![charset.to_vec(); length],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// The first `len` positions of the mask.
+    pub fn prefix(&self, len: usize) -> Mask {
+        Mask {
+            positions: self.positions[..len].to_vec(),
+        }
+    }
+
+    /// Number of candidates the mask produces, if it fits in 64 bits.
+    pub fn keyspace(&self) -> Option<u64> {
+        self.positions
+            .iter()
+            .try_fold(1u64, |acc, set| acc.checked_mul(set.len() as u64))
+    }
+
+    /// Writes the candidate with the given index into `buf`, which must be
+    /// `len()` bytes long. The index is decoded in mixed radix with the last
+    /// position varying fastest.
+    pub fn index_to_candidate(&self, mut index: u64, buf: &mut [u8]) {
+        for (byte, set) in buf.iter_mut().zip(&self.positions).rev() {
+            *byte = set[(index % set.len() as u64) as usize];
+            index /= set.len() as u64;
+        }
+    }
+}
+
+fn charset_class(class: u8, custom: &[Option<Vec<u8>>; 4]) -> Result<Vec<u8>, String> {
+    let set = match class {
+        b'l' => LOWER.to_vec(),
+        b'u' => UPPER.to_vec(),
+        b'd' => DIGITS.to_vec(),
+        b'h' => b"0123456789abcdef".to_vec(),
+        b'H' => b"0123456789ABCDEF".to_vec(),
+        b's' => SPECIAL.to_vec(),
+        b'a' => [LOWER, UPPER, DIGITS, SPECIAL].concat(),
+        b'b' => (0..=255).collect(),
+        b'?' => vec![b'?'],
+        b'1'..=b'4' => custom[(class - b'1') as usize]
+            .clone()
+            .ok_or_else(|| format!("Custom charset ?{} is not defined", class as char))?,
+        _ => return Err(format!("Unknown charset ?{}", class as char)),
+    };
+    Ok(set)
+}
+
+/// Expands built-in classes in a custom charset definition such as `?l?d_`
+/// and drops repeated characters.
+fn expand_charset(def: &[u8], custom: &[Option<Vec<u8>>; 4]) -> Result<Vec<u8>, String> {
+    let mut set = Vec::new();
+    let mut bytes = def.iter().copied();
+    while let Some(b) = bytes.next() {
+        if b == b'?' {
+            let class = bytes.next().ok_or("Charset ends with a lone '?'")?;
+            set.extend(charset_class(class, custom)?);
+        } else {
+            set.push(b);
+        }
+    }
+
+    let mut seen = [false; 256];
+    set.retain(|&b| !std::mem::replace(&mut seen[b as usize], true));
+    if set.is_empty() {
+        return Err("Empty charset".to_string());
+    }
+    Ok(set)
+}
@@ -1,4 +1,9 @@
-pub fn hash_md5(input: &str) -> String {
-    let digest = md5::compute(input.as_bytes());
-    format!("{:x}", digest)
-}
+use crate::algorithm::{DigestAlgorithm, hash_with};
+
+pub static MD5: DigestAlgorithm = DigestAlgorithm {
+    name: "md5",
+    label: "MD5",
+    len: 16,
+    hash: hash_with::<md5::Md5>,
+    detect: true,
+};
@@ -0,0 +1,224 @@
+//! Live counters of a running job, updated by the worker threads as they go
+//! so a status display can sample them at any time, however long a batch
+//! takes.
+
+use crate::cracker::display_candidate;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Longest a worker holds back its count; shorter for slow hashes, where
+/// every candidate matters to the display.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+const MAX_FLUSH_EVERY: u64 = 1 << 16;
+
+/// Handle to the counters of a job, see [`crate::Job::monitor`]. Clones
+/// share the counters.
+#[derive(Clone)]
+pub struct Monitor(Arc<Shared>);
+
+struct Shared {
+    done: AtomicU64,
+    current: Mutex<Vec<u8>>,
+    stage: Mutex<Stage>,
+    completed_lengths: Mutex<Vec<LengthStats>>,
+}
+
+#[derive(Clone, Copy)]
+struct Stage {
+    start: Instant,
+    total: Option<u64>,
+    fraction: Option<f64>,
+    length: Option<LengthStage>,
+    cracked: usize,
+    targets: usize,
+}
+
+/// The length being enumerated: `total` candidates from when the job had
+/// checked `base`.
+#[derive(Clone, Copy)]
+struct LengthStage {
+    length: usize,
+    base: u64,
+    total: u64,
+    start: Instant,
+}
+
+/// Candidates of one length checked, out of the `total` the job enumerates
+/// of that length, in `elapsed`.
+#[derive(Clone, Copy)]
+pub struct LengthStats {
+    pub length: usize,
+    pub done: u64,
+    pub total: u64,
+    pub elapsed: Duration,
+}
+
+/// State of a job at one point in time.
+#[derive(Clone)]
+pub struct Snapshot {
+    /// Candidates checked, across every length and including those checked
+    /// before a restore.
+    pub done: u64,
+    /// Candidates the whole job checks, when known.
+    pub total: Option<u64>,
+    /// Fraction of the job done: `done / total` when the total is known,
+    /// the share of the wordlist read otherwise.
+    pub fraction: Option<f64>,
+    /// Length of the candidates being enumerated, for brute force and masks.
+    pub length: Option<usize>,
+    /// Progress through that length.
+    pub current_length: Option<LengthStats>,
+    /// Lengths enumerated to the end since the job started, in order.
+    pub completed_lengths: Vec<LengthStats>,
+    /// A candidate checked recently.
+    pub current: Option<String>,
+    pub cracked: usize,
+    pub targets: usize,
+    pub elapsed: Duration,
+}
+
+impl Monitor {
+    pub(crate) fn new() -> Self {
+        Monitor(Arc::new(Shared {
+            done: AtomicU64::new(0),
+            current: Mutex::new(Vec::new()),
+            stage: Mutex::new(Stage {
+                start: Instant::now(),
+                total: None,
+                fraction: None,
+                length: None,
+                cracked: 0,
+                targets: 0,
+            }),
+            completed_lengths: Mutex::new(Vec::new()),
+        }))
+    }
+
+    /// Starts the clock as the job starts running.
+    pub(crate) fn start(&self, targets: usize, cracked: usize) {
+        let mut stage = self.0.stage.lock().unwrap();
+        stage.start = Instant::now();
+        stage.targets = targets;
+        stage.cracked = cracked;
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let stage = *self.0.stage.lock().unwrap();
+        let done = self.0.done.load(Ordering::Relaxed);
+        let current = self.0.current.lock().unwrap();
+        let fraction = match stage.total {
+            Some(0) => Some(1.0),
+            Some(total) => Some(done as f64 / total as f64),
+            None => stage.fraction,
+        };
+        let current_length = stage.length.map(|length| LengthStats {
+            length: length.length,
+            done: done.saturating_sub(length.base).min(length.total),
+            total: length.total,
+            elapsed: length.start.elapsed(),
+        });
+        Snapshot {
+            done,
+            total: stage.total,
+            fraction,
+            length: current_length.map(|length| length.length),
+            current_length,
+            completed_lengths: self.0.completed_lengths.lock().unwrap().clone(),
+            current: (!current.is_empty()).then(|| display_candidate(&current)),
+            cracked: stage.cracked,
+            targets: stage.targets,
+            elapsed: stage.start.elapsed(),
+        }
+    }
+
+    /// Sets the number of candidates checked. Only called between batches,
+    /// while no worker holds a [`Counter`].
+    pub(crate) fn set_done(&self, done: u64) {
+        self.0.done.store(done, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_total(&self, total: Option<u64>) {
+        self.0.stage.lock().unwrap().total = total;
+    }
+
+    pub(crate) fn set_fraction(&self, fraction: Option<f64>) {
+        self.0.stage.lock().unwrap().fraction = fraction;
+    }
+
+    /// Starts enumerating `total` candidates of `length`, counted from the
+    /// candidates checked so far.
+    pub(crate) fn start_length(&self, length: usize, total: u64) {
+        self.0.stage.lock().unwrap().length = Some(LengthStage {
+            length,
+            base: self.0.done.load(Ordering::Relaxed),
+            total,
+            start: Instant::now(),
+        });
+    }
+
+    /// Moves the length being enumerated, once checked to the end, to the
+    /// completed ones.
+    pub(crate) fn finish_length(&self) {
+        let Some(length) = self.snapshot().current_length else {
+            return;
+        };
+        self.0.stage.lock().unwrap().length = None;
+        self.0.completed_lengths.lock().unwrap().push(length);
+    }
+
+    pub(crate) fn set_cracked(&self, cracked: usize) {
+        self.0.stage.lock().unwrap().cracked = cracked;
+    }
+
+    /// A counter for one worker to tick for every candidate it checks.
+    pub(crate) fn counter(&self) -> Counter<'_> {
+        Counter {
+            shared: &self.0,
+            pending: 0,
+            every: 1,
+            last_flush: Instant::now(),
+        }
+    }
+}
+
+/// Per-worker candidate count, added to the shared one every `every`
+/// candidates so fast hashes do not contend on it. `every` adapts so a flush
+/// happens roughly every [`FLUSH_INTERVAL`].
+pub(crate) struct Counter<'a> {
+    shared: &'a Shared,
+    pending: u64,
+    every: u64,
+    last_flush: Instant,
+}
+
+impl Counter<'_> {
+    pub fn tick(&mut self, candidate: &[u8]) {
+        self.pending += 1;
+        if self.pending < self.every {
+            return;
+        }
+
+        self.shared.done.fetch_add(self.pending, Ordering::Relaxed);
+        self.pending = 0;
+        if let Ok(mut current) = self.shared.current.try_lock() {
+            current.clear();
+            current.extend_from_slice(candidate);
+        }
+
+        let now = Instant::now();
+        let since = now - self.last_flush;
+        if since < FLUSH_INTERVAL / 4 {
+            self.every = (self.every * 2).min(MAX_FLUSH_EVERY);
+        } else if since > FLUSH_INTERVAL {
+            self.every = (self.every / 2).max(1);
+        }
+        self.last_flush = now;
+    }
+}
+
+impl Drop for Counter<'_> {
+    fn drop(&mut self) {
+        self.shared.done.fetch_add(self.pending, Ordering::Relaxed);
+    }
+}
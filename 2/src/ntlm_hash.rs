@@ -0,0 +1,186 @@
+//! Windows NTLM hashes and NetNTLMv2 challenge-responses, both built on the
+//! MD4 of the password encoded as UTF-16LE.
+
+use crate::algorithm::{Digest, DigestAlgorithm, HashAlgorithm, HashOptions, Target};
+use hmac::{Hmac, Mac};
+use md4::{Digest as _, Md4};
+
+type HmacMd5 = Hmac<md5::Md5>;
+
+/// Looks exactly like MD5, so only `--algo` selects it.
+pub static NTLM: DigestAlgorithm = DigestAlgorithm {
+    name: "ntlm",
+    label: "NTLM",
+    len: 16,
+    hash: hash_ntlm,
+    detect: false,
+};
+
+pub struct NetNtlmV2;
+
+/// Feeds `password` to `sink` as UTF-16LE, a chunk at a time so checking a
+/// candidate never allocates. Passwords that are not valid UTF-8 are taken
+/// as Latin-1, a byte per character.
+fn for_each_utf16le(password: &[u8], mut sink: impl FnMut(&[u8])) {
+    let mut buf = [0u8; 128];
+    let mut len = 0;
+    let mut push = |unit: u16| {
+        if len == buf.len() {
+            sink(&buf);
+            len = 0;
+        }
+        buf[len..len + 2].copy_from_slice(&unit.to_le_bytes());
+        len += 2;
+    };
+    match std::str::from_utf8(password) {
+        Ok(text) => text.encode_utf16().for_each(&mut push),
+        Err(_) => password.iter().for_each(|&b| push(b as u16)),
+    }
+    sink(&buf[..len]);
+}
+
+fn utf16le(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for_each_utf16le(text.as_bytes(), |chunk| bytes.extend_from_slice(chunk));
+    bytes
+}
+
+/// The NT hash: MD4 of the UTF-16LE password.
+pub fn hash_ntlm(password: &[u8]) -> Digest {
+    let mut md4 = Md4::new();
+    for_each_utf16le(password, |chunk| md4.update(chunk));
+    Digest::new(&md4.finalize())
+}
+
+fn hmac_md5(key: &[u8], parts: &[&[u8]]) -> [u8; 16] {
+    let mut mac = HmacMd5::new_from_slice(key).expect("HMAC takes keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+/// A `user::domain:challenge:ntproofstr:blob` line, decoded once.
+struct NetNtlmV2Target {
+    /// UTF-16LE of the uppercased user name followed by the domain.
+    identity: Vec<u8>,
+    /// The server challenge followed by the client blob.
+    challenge_blob: Vec<u8>,
+    proof: [u8; 16],
+}
+
+fn parse_netntlmv2(hash: &str) -> Result<NetNtlmV2Target, String> {
+    let fields: Vec<&str> = hash.split(':').collect();
+    let [user, "", domain, challenge, proof, blob] = fields[..] else {
+        return Err("NetNTLMv2 hash must be user::domain:challenge:ntproofstr:blob".to_string());
+    };
+    let decode = |field: &str, value: &str, len: Option<usize>| {
+        let bytes =
+            hex::decode(value).map_err(|e| format!("Invalid NetNTLMv2 {}: {}", field, e))?;
+        match len {
+            Some(len) if bytes.len() != len => Err(format!(
+                "Invalid NetNTLMv2 {}: expected {} hex characters",
+                field,
+                len * 2
+            )),
+            None if bytes.is_empty() => Err(format!("Invalid NetNTLMv2 {}: empty", field)),
+            _ => Ok(bytes),
+        }
+    };
+    let mut challenge_blob = decode("challenge", challenge, Some(8))?;
+    let proof = decode("ntproofstr", proof, Some(16))?;
+    challenge_blob.extend(decode("blob", blob, None)?);
+
+    Ok(NetNtlmV2Target {
+        identity: utf16le(&(user.to_uppercase() + domain)),
+        challenge_blob,
+        proof: proof.try_into().expect("length checked"),
+    })
+}
+
+impl HashAlgorithm for NetNtlmV2 {
+    fn name(&self) -> &'static str {
+        "netntlmv2"
+    }
+
+    fn identify(&self, hash: &str) -> bool {
+        parse_netntlmv2(hash).is_ok()
+    }
+
+    fn prepare(&self, hash: &str, _options: &HashOptions) -> Result<Box<dyn Target>, String> {
+        Ok(Box::new(parse_netntlmv2(hash)?))
+    }
+
+    fn generation_error(&self) -> Option<String> {
+        Some(
+            "NetNTLMv2 is a challenge-response: it depends on the server challenge and the \
+             client blob, so it cannot be generated from a password alone"
+                .to_string(),
+        )
+    }
+}
+
+impl Target for NetNtlmV2Target {
+    fn check(&self, candidate: &[u8]) -> bool {
+        let key = hmac_md5(hash_ntlm(candidate).as_bytes(), &[&self.identity]);
+        hmac_md5(&key, &[&self.challenge_blob]) == self.proof
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// hashcat's example hash for mode 5600, of "hashcat".
+    const NETNTLMV2: &str = "admin::N46iSNekpT:08ca45b7d7ea58ee:88dcbe4446168966a153a0064958dac6:5c7830315c7830310000000000000b45c67103d07d7b95acd12ffa11230e0000000052920b85f78d013c31cdb3b92f5d765c783030";
+
+    fn ntlm(password: &[u8]) -> String {
+        hex::encode(hash_ntlm(password).as_bytes())
+    }
+
+    #[test]
+    fn hashes_utf16le() {
+        assert_eq!(ntlm(b"password"), "8846f7eaee8fb117ad06bdd830b7586c");
+        assert_eq!(ntlm(b""), "31d6cfe0d16ae931b73c59d7e0c089c0");
+        assert_eq!(
+            ntlm("пароль".as_bytes()),
+            "507e3ee80df7db7c1fdd8d50ae8db606"
+        );
+        // Longer than one chunk of the UTF-16LE buffer.
+        assert_eq!(
+            ntlm("ab".repeat(50).as_bytes()),
+            "79907e6390113ade3e5153a9fee36d5a"
+        );
+    }
+
+    #[test]
+    fn takes_invalid_utf8_as_latin1() {
+        assert_eq!(ntlm(b"caf\xe9"), ntlm("café".as_bytes()));
+    }
+
+    #[test]
+    fn verifies_netntlmv2() {
+        let target = parse_netntlmv2(NETNTLMV2).unwrap();
+        assert!(target.check(b"hashcat"));
+        assert!(!target.check(b"Hashcat"));
+        assert!(NetNtlmV2.identify(NETNTLMV2));
+    }
+
+    #[test]
+    fn uppercases_only_the_user() {
+        let mixed_user = NETNTLMV2.replacen("admin", "AdMiN", 1);
+        assert!(parse_netntlmv2(&mixed_user).unwrap().check(b"hashcat"));
+        let lower_domain = NETNTLMV2.replacen("N46iSNekpT", "n46isnekpt", 1);
+        assert!(!parse_netntlmv2(&lower_domain).unwrap().check(b"hashcat"));
+    }
+
+    #[test]
+    fn rejects_malformed_netntlmv2() {
+        let short_challenge = NETNTLMV2.replacen(":08ca45b7d7ea58ee:", ":08ca45b7:", 1);
+        let no_blank = NETNTLMV2.replacen("::", ":", 1);
+        let no_blob = &NETNTLMV2[..NETNTLMV2.rfind(':').unwrap() + 1];
+        for hash in [short_challenge.as_str(), no_blank.as_str(), no_blob] {
+            assert!(parse_netntlmv2(hash).is_err(), "{}", hash);
+        }
+    }
+}
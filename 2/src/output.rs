@@ -0,0 +1,182 @@
+//! Reporting of a running job: human-readable lines on stdout and an
+//! optional JSON Lines event log for dashboards.
+
+use hash_brute_force::{Event, Job, Outcome, Status};
+use serde_json::{Value, json};
+use std::fs::{File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Prints the events of a running job in the same format for every mode.
+/// Progress is left to the status display.
+pub struct Printer {
+    single_target: bool,
+    start: Instant,
+}
+
+impl Printer {
+    pub fn new(single_target: bool) -> Self {
+        Printer {
+            single_target,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn on_event(&mut self, event: &Event) {
+        match event {
+            Event::LengthStarted {
+                length,
+                range,
+                combinations,
+            } => {
+                if *range == (0..*combinations) {
+                    println!("Trying length {}: {} combinations", length, combinations);
+                } else {
+                    println!(
+                        "Trying length {}: {} of {} combinations ({}..{})",
+                        length,
+                        range.end.saturating_sub(range.start),
+                        combinations,
+                        range.start,
+                        range.end
+                    );
+                }
+            }
+            Event::LengthComplete { length } => {
+                println!(
+                    "  Length {} complete - {:.2?}",
+                    length,
+                    self.start.elapsed()
+                );
+            }
+            Event::WordlistStarted {
+                path,
+                rules,
+                resumed_words,
+            } => {
+                println!("Wordlist: {}", path.display());
+                if let Some(rules) = rules {
+                    println!("Rules: {}", rules);
+                }
+                if *resumed_words > 0 {
+                    println!("  Resuming after {} words", resumed_words);
+                }
+            }
+            Event::WordlistComplete { words } => {
+                println!(
+                    "  Wordlist complete: {} words - {:.2?}",
+                    words,
+                    self.start.elapsed()
+                );
+            }
+            Event::Progress(_) => {}
+            Event::Paused => println!("Paused, press r to resume"),
+            Event::Resumed => println!("Resumed"),
+            Event::Found(crack) => {
+                if self.single_target {
+                    println!("\n✅ SUCCESS! Password found: {}", crack.password);
+                } else {
+                    println!("✅ {}: {}", crack.hash, crack.password);
+                }
+            }
+            Event::Warning(message) => eprintln!("{}", message),
+        }
+    }
+}
+
+/// Writes one JSON object per line for the start of a job, its progress,
+/// every password found and how it ended.
+pub struct EventLog {
+    file: LineWriter<File>,
+    start: Instant,
+    length: Option<usize>,
+    failed: bool,
+}
+
+impl EventLog {
+    /// Opens the log at `path` for appending, so a restored session
+    /// continues the same stream.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        Ok(EventLog {
+            file: LineWriter::new(file),
+            start: Instant::now(),
+            length: None,
+            failed: false,
+        })
+    }
+
+    pub fn start(&mut self, job: &Job) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let keyspace = job.keyspace().map(
+            |(slice, total)| json!({ "start": slice.start, "end": slice.end, "total": total }),
+        );
+        self.write(json!({
+            "event": "start",
+            "time": time,
+            "algorithm": job.targets().algorithm().name(),
+            "targets": job.total_targets(),
+            "cracked": job.found().len(),
+            "keyspace": keyspace,
+            "threads": job.threads(),
+        }));
+    }
+
+    pub fn on_event(&mut self, event: &Event) {
+        match event {
+            Event::LengthStarted { length, .. } => self.length = Some(*length),
+            Event::WordlistStarted { .. } => self.length = None,
+            Event::Progress(progress) => self.write(json!({
+                "event": "progress",
+                "length": self.length,
+                "done": progress.done,
+                "total": progress.total,
+                "fraction": progress.fraction,
+                "elapsed": progress.elapsed.as_secs_f64(),
+            })),
+            Event::Found(crack) => self.write(json!({
+                "event": "found",
+                "hash": crack.hash,
+                "password": crack.password,
+                "elapsed": self.start.elapsed().as_secs_f64(),
+            })),
+            Event::Paused | Event::Resumed => self.write(json!({
+                "event": if matches!(event, Event::Paused) { "paused" } else { "resumed" },
+                "elapsed": self.start.elapsed().as_secs_f64(),
+            })),
+            _ => {}
+        }
+    }
+
+    pub fn finish(&mut self, outcome: &Outcome) {
+        let event = match outcome.status {
+            Status::AllCracked => "cracked",
+            Status::Exhausted => "exhausted",
+            Status::Cancelled => "cancelled",
+        };
+        self.write(json!({
+            "event": event,
+            "found": outcome.found.len(),
+            "targets": outcome.total_targets,
+            "elapsed": outcome.elapsed.as_secs_f64(),
+        }));
+    }
+
+    fn write(&mut self, value: Value) {
+        if self.failed {
+            return;
+        }
+        if let Err(e) = writeln!(self.file, "{}", value) {
+            // Reported once; the search itself goes on without the log.
+            eprintln!("Failed to write event log: {}", e);
+            self.failed = true;
+        }
+    }
+}
@@ -0,0 +1,439 @@
+//! PBKDF2-HMAC with SHA-1, SHA-256 or SHA-512, in the encodings web
+//! frameworks store it in:
+//!
+//! - Django: `pbkdf2_sha256$<iterations>$<salt>$<base64>`, the salt used as
+//!   written;
+//! - passlib: `$pbkdf2-sha256$<iterations>$<salt>$<digest>`, salt and digest
+//!   in passlib's base64 with `.` for `+`, `$pbkdf2$` for SHA-1;
+//! - PHC: `$pbkdf2-sha256$i=<iterations>,l=<length>$<salt>$<digest>`.
+
+use crate::algorithm::{GenerateOptions, HashAlgorithm, HashOptions, Target};
+use crate::detect;
+use base64::Engine;
+use base64::alphabet::{Alphabet, STANDARD};
+use base64::engine::general_purpose::STANDARD as PADDED;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use std::str::FromStr;
+
+pub struct Pbkdf2;
+
+const CONFIG: GeneralPurposeConfig = GeneralPurposeConfig::new()
+    .with_encode_padding(false)
+    .with_decode_padding_mode(DecodePaddingMode::Indifferent);
+
+/// Standard base64, padded in Django hashes and unpadded in PHC strings.
+const BASE64: GeneralPurpose = GeneralPurpose::new(&STANDARD, CONFIG);
+
+/// passlib's "adapted" base64: standard base64 with `.` for `+`.
+const AB64_ALPHABET: Alphabet =
+    match Alphabet::new("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789./") {
+        Ok(alphabet) => alphabet,
+        Err(_) => panic!("invalid passlib base64 alphabet"),
+    };
+const AB64: GeneralPurpose = GeneralPurpose::new(&AB64_ALPHABET, CONFIG);
+
+/// The HMAC hash function.
+#[derive(Clone, Copy)]
+enum Prf {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl Prf {
+    /// Parses the suffix of `pbkdf2_sha256` or `pbkdf2-sha256`; a bare
+    /// `pbkdf2` is SHA-1.
+    fn from_id(id: &str) -> Option<Prf> {
+        match id {
+            "pbkdf2" | "pbkdf2-sha1" | "pbkdf2_sha1" => Some(Prf::Sha1),
+            "pbkdf2-sha256" | "pbkdf2_sha256" => Some(Prf::Sha256),
+            "pbkdf2-sha512" | "pbkdf2_sha512" => Some(Prf::Sha512),
+            _ => None,
+        }
+    }
+
+    /// Parses a hash function name: `sha1`, `sha256` or `sha512`.
+    fn from_name(name: &str) -> Result<Prf, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "sha1" => Ok(Prf::Sha1),
+            "sha256" => Ok(Prf::Sha256),
+            "sha512" => Ok(Prf::Sha512),
+            _ => Err(format!(
+                "Unknown PBKDF2 hash function '{}', expected sha1, sha256 or sha512",
+                name
+            )),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Prf::Sha1 => "sha1",
+            Prf::Sha256 => "sha256",
+            Prf::Sha512 => "sha512",
+        }
+    }
+
+    /// The id of passlib and PHC hashes, `pbkdf2` alone for SHA-1.
+    fn crypt_id(self) -> &'static str {
+        match self {
+            Prf::Sha1 => "pbkdf2",
+            Prf::Sha256 => "pbkdf2-sha256",
+            Prf::Sha512 => "pbkdf2-sha512",
+        }
+    }
+
+    /// Bytes of output the frameworks keep, the size of one HMAC.
+    fn output_len(self) -> usize {
+        match self {
+            Prf::Sha1 => 20,
+            Prf::Sha256 => 32,
+            Prf::Sha512 => 64,
+        }
+    }
+
+    fn derive(self, password: &[u8], salt: &[u8], rounds: u32, out: &mut [u8]) {
+        match self {
+            Prf::Sha1 => pbkdf2::pbkdf2_hmac::<sha1::Sha1>(password, salt, rounds, out),
+            Prf::Sha256 => pbkdf2::pbkdf2_hmac::<sha2::Sha256>(password, salt, rounds, out),
+            Prf::Sha512 => pbkdf2::pbkdf2_hmac::<sha2::Sha512>(password, salt, rounds, out),
+        }
+    }
+}
+
+/// A hash in any of the encodings, decoded once.
+struct Pbkdf2Target {
+    prf: Prf,
+    rounds: u32,
+    salt: Vec<u8>,
+    expected: Vec<u8>,
+}
+
+fn parse(hash: &str) -> Result<Pbkdf2Target, String> {
+    let invalid = |reason: String| format!("Invalid PBKDF2 hash: {}", reason);
+    let (django, rest) = match hash.strip_prefix('$') {
+        Some(rest) => (false, rest),
+        None => (true, hash),
+    };
+    let mut parts = rest.split('$');
+    let (Some(id), Some(params), Some(salt), Some(digest), None) = (
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+    ) else {
+        return Err(invalid(
+            "expected <algorithm>$<iterations>$<salt>$<digest>".to_string(),
+        ));
+    };
+    let prf = Prf::from_id(id)
+        .filter(|_| id.contains('_') == django)
+        .ok_or_else(|| invalid(format!("unknown algorithm '{}'", id)))?;
+
+    let (rounds, len, salt, expected) = if django {
+        let digest = BASE64
+            .decode(digest)
+            .map_err(|e| invalid(format!("digest is not valid base64: {}", e)))?;
+        (params, None, salt.as_bytes().to_vec(), digest)
+    } else if params.contains('=') {
+        let (mut rounds, mut len) = (None, None);
+        for param in params.split(',') {
+            match param.split_once('=') {
+                Some(("i", value)) => rounds = Some(value),
+                Some(("l", value)) => len = Some(value),
+                _ => return Err(invalid(format!("unknown parameter '{}'", param))),
+            }
+        }
+        let rounds = rounds.ok_or_else(|| invalid("no i=<iterations> parameter".to_string()))?;
+        let decode = |field: &str, encoded: &str| {
+            BASE64
+                .decode(encoded)
+                .map_err(|e| invalid(format!("{} is not valid base64: {}", field, e)))
+        };
+        (
+            rounds,
+            len,
+            decode("salt", salt)?,
+            decode("digest", digest)?,
+        )
+    } else {
+        let decode = |field: &str, encoded: &str| {
+            AB64.decode(encoded)
+                .map_err(|e| invalid(format!("{} is not valid passlib base64: {}", field, e)))
+        };
+        (
+            params,
+            None,
+            decode("salt", salt)?,
+            decode("digest", digest)?,
+        )
+    };
+
+    let rounds = rounds
+        .parse::<u32>()
+        .ok()
+        .filter(|&rounds| rounds > 0)
+        .ok_or_else(|| invalid(format!("iterations '{}' is not a positive number", rounds)))?;
+    if expected.is_empty() {
+        return Err(invalid("digest is empty".to_string()));
+    }
+    if let Some(len) = len
+        && len.parse() != Ok(expected.len())
+    {
+        return Err(invalid(format!(
+            "length l={} does not match the {}-byte digest",
+            len,
+            expected.len()
+        )));
+    }
+    Ok(Pbkdf2Target {
+        prf,
+        rounds,
+        salt,
+        expected,
+    })
+}
+
+/// The encodings [`hash_pbkdf2`] writes.
+#[derive(Clone, Copy)]
+pub enum Format {
+    Django,
+    Passlib,
+    Phc,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "django" => Ok(Format::Django),
+            "passlib" => Ok(Format::Passlib),
+            "phc" => Ok(Format::Phc),
+            _ => Err(format!(
+                "Unknown PBKDF2 format '{}', expected django, passlib or phc",
+                s
+            )),
+        }
+    }
+}
+
+/// Iterations of generated hashes when not given, OWASP's recommendation
+/// for PBKDF2-HMAC-SHA256.
+pub const DEFAULT_ROUNDS: u32 = 600_000;
+
+/// Characters of the random salts of Django hashes, as Django uses.
+const DJANGO_SALT_LEN: usize = 22;
+const ALPHANUMERIC: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+/// Bytes of the random salts of passlib and PHC hashes.
+const SALT_LEN: usize = 16;
+
+/// Hashes `password` with PBKDF2-HMAC-`hash` (`sha1`, `sha256` or `sha512`)
+/// into a hash of `format`. `salt` is used as is, random when omitted.
+pub fn hash_pbkdf2(
+    password: &[u8],
+    hash: &str,
+    rounds: u32,
+    salt: Option<&str>,
+    format: Format,
+) -> Result<String, String> {
+    let prf = Prf::from_name(hash)?;
+    if rounds == 0 {
+        return Err("PBKDF2 iterations must be positive".to_string());
+    }
+    let salt = match salt {
+        Some(salt) if salt.contains('$') => {
+            return Err("PBKDF2 salt must not contain '$'".to_string());
+        }
+        Some(salt) => salt.as_bytes().to_vec(),
+        None => {
+            let len = match format {
+                Format::Django => DJANGO_SALT_LEN,
+                Format::Passlib | Format::Phc => SALT_LEN,
+            };
+            let mut bytes = vec![0u8; len];
+            getrandom::getrandom(&mut bytes)
+                .map_err(|e| format!("Failed to generate a salt: {}", e))?;
+            if let Format::Django = format {
+                // Django keeps the salt as text, drawn from letters and
+                // digits.
+                for b in &mut bytes {
+                    *b = ALPHANUMERIC[*b as usize % ALPHANUMERIC.len()];
+                }
+            }
+            bytes
+        }
+    };
+    let mut output = vec![0u8; prf.output_len()];
+    prf.derive(password, &salt, rounds, &mut output);
+
+    Ok(match format {
+        Format::Django => format!(
+            "pbkdf2_{}${}${}${}",
+            prf.name(),
+            rounds,
+            String::from_utf8_lossy(&salt),
+            PADDED.encode(&output)
+        ),
+        Format::Passlib => format!(
+            "${}${}${}${}",
+            prf.crypt_id(),
+            rounds,
+            AB64.encode(&salt),
+            AB64.encode(&output)
+        ),
+        Format::Phc => format!(
+            "${}$i={},l={}${}${}",
+            prf.crypt_id(),
+            rounds,
+            output.len(),
+            BASE64.encode(&salt),
+            BASE64.encode(&output)
+        ),
+    })
+}
+
+/// Checks `password` against a PBKDF2 hash in any of the encodings.
+pub fn verify_pbkdf2(password: &[u8], hash: &str) -> bool {
+    parse(hash).is_ok_and(|target| target.check(password))
+}
+
+impl HashAlgorithm for Pbkdf2 {
+    fn name(&self) -> &'static str {
+        "pbkdf2"
+    }
+
+    fn identify(&self, hash: &str) -> bool {
+        let id = detect::crypt_id(hash).or_else(|| hash.split_once('$').map(|(id, _)| id));
+        id.and_then(Prf::from_id).is_some() && parse(hash).is_ok()
+    }
+
+    fn prepare(&self, hash: &str, _options: &HashOptions) -> Result<Box<dyn Target>, String> {
+        Ok(Box::new(parse(hash)?))
+    }
+
+    fn generation_error(&self) -> Option<String> {
+        None
+    }
+
+    fn generate(&self, password: &[u8], options: &GenerateOptions) -> Result<String, String> {
+        hash_pbkdf2(
+            password,
+            options.variant.as_deref().unwrap_or("sha256"),
+            options.rounds.unwrap_or(DEFAULT_ROUNDS),
+            options.salt.as_deref(),
+            options.pbkdf2_format.parse()?,
+        )
+    }
+}
+
+impl Target for Pbkdf2Target {
+    fn check(&self, candidate: &[u8]) -> bool {
+        let mut output = vec![0u8; self.expected.len()];
+        self.prf
+            .derive(candidate, &self.salt, self.rounds, &mut output);
+        output == self.expected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hashes of "password" in every encoding, by Python's hashlib. The
+    /// passlib salts and digests hold `.` where base64 has `+`.
+    const VECTORS: &[&str] = &[
+        "pbkdf2_sha1$1000$saltsalt$6f6/9Uv85mj94wGsyFVjzJ3HHvY=",
+        "pbkdf2_sha256$1000$saltsalt$E196ZhRPzw+wA84EjzHwJO1cv/MFJdO6C/sxmUeTYqY=",
+        "pbkdf2_sha512$1000$saltsalt$Q6v4xwJ8a9nWPp2BeEoAYYhHSo2xRmPWART17vTpSxt2q6iNp7BOozW557qqa95eNjUO4gKs0CyvJbYGGku1tA==",
+        "$pbkdf2$1000$.Pvvc2FsdAA$d2vBxDZHckE4zER.zic3ncBV9EI",
+        "$pbkdf2-sha256$1000$.fvvc2FsdAA$gnvLHp5ZCZFLrHVFDxvcwk..6W14Pko60e//QeDW5so",
+        "$pbkdf2-sha512$1000$.Pvvc2FsdAA$j/mrXfDnlVjviu1K4rzxm9y2m9FANpChFWqlfAhg0zthUsMvkWilxQ5kqzzyQwXuAtxXdWCFP0Iyh5a.W92Ojw",
+        "$pbkdf2$i=1000,l=20$c2FsdHNhbHRzYWx0c2FsdA$2FWw/oC7TQkskizC+81lWlmFAMM",
+        "$pbkdf2-sha256$i=1000,l=32$c2FsdHNhbHRzYWx0c2FsdA$8nX7hwFEzIB8aPajJTYK8weHQc5Ngz0pFVAKvSu4jQA",
+        "$pbkdf2-sha512$i=1000,l=64$c2FsdHNhbHRzYWx0c2FsdA$715rqIr5dXOVPpBhqqsugl037zT5bWJTWYmZtIcK8hBnisKpwfY7kokvwjDrNHqHhF50Pb7MD6HvkJwiDQw4ww",
+    ];
+
+    #[test]
+    fn verifies_every_encoding() {
+        for hash in VECTORS {
+            assert!(Pbkdf2.identify(hash), "{}", hash);
+            assert!(verify_pbkdf2(b"password", hash), "{}", hash);
+            assert!(!verify_pbkdf2(b"Password", hash), "{}", hash);
+        }
+    }
+
+    #[test]
+    fn accepts_phc_parameters_in_any_order() {
+        let reordered = "$pbkdf2-sha256$l=32,i=1000$c2FsdHNhbHRzYWx0c2FsdA$8nX7hwFEzIB8aPajJTYK8weHQc5Ngz0pFVAKvSu4jQA";
+        assert!(verify_pbkdf2(b"password", reordered));
+        let no_length = "$pbkdf2-sha256$i=1000$c2FsdHNhbHRzYWx0c2FsdA$8nX7hwFEzIB8aPajJTYK8weHQc5Ngz0pFVAKvSu4jQA";
+        assert!(verify_pbkdf2(b"password", no_length));
+    }
+
+    #[test]
+    fn generates_every_encoding() {
+        let cases = [
+            ("sha256", Some("saltsalt"), Format::Django, VECTORS[1]),
+            ("sha1", Some("saltsaltsaltsalt"), Format::Phc, VECTORS[6]),
+        ];
+        for (prf, salt, format, expected) in cases {
+            assert_eq!(
+                hash_pbkdf2(b"password", prf, 1000, salt, format).unwrap(),
+                expected
+            );
+        }
+        for format in [Format::Django, Format::Passlib, Format::Phc] {
+            let hash = hash_pbkdf2(b"password", "sha512", 10, None, format).unwrap();
+            assert!(verify_pbkdf2(b"password", &hash), "{}", hash);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_hashes() {
+        let cases = [
+            (
+                "$pbkdf2-sha256$i=1000,l=31$c2FsdHNhbHRzYWx0c2FsdA$8nX7hwFEzIB8aPajJTYK8weHQc5Ngz0pFVAKvSu4jQA",
+                "l=31 does not match",
+            ),
+            (
+                "$pbkdf2-sha256$i=0,l=32$c2FsdHNhbHRzYWx0c2FsdA$8nX7hwFEzIB8aPajJTYK8weHQc5Ngz0pFVAKvSu4jQA",
+                "iterations '0'",
+            ),
+            (
+                "$pbkdf2-sha256$m=1,i=1000$c2FsdHNhbHRzYWx0c2FsdA$8nX7hwFEzIB8aPajJTYK8weHQc5Ngz0pFVAKvSu4jQA",
+                "unknown parameter",
+            ),
+            (
+                "pbkdf2-sha256$1000$saltsalt$E196ZhRPzw+wA84EjzHwJO1cv/MFJdO6C/sxmUeTYqY=",
+                "unknown algorithm",
+            ),
+            (
+                "$pbkdf2_sha256$1000$.fvvc2FsdAA$gnvLHp5ZCZFLrHVFDxvcwk..6W14Pko60e//QeDW5so",
+                "unknown algorithm",
+            ),
+            (
+                "$pbkdf2_sha256$1000$saltsalt$E196ZhRPzw+wA84EjzHwJO1cv/MFJdO6C/sxmUeTYqY=",
+                "unknown algorithm",
+            ),
+            (
+                "pbkdf2_sha256$1000$saltsalt$E196ZhRPzw+wA84EjzHwJO1cv/MFJdO6C/sxmUeTYqY!",
+                "not valid base64",
+            ),
+            (
+                "$pbkdf2-sha256$1000$.fvvc2FsdAA$gnvLHp5ZCZFLrHVFDxvcwk++6W14Pko60e//QeDW5so",
+                "not valid passlib base64",
+            ),
+            ("pbkdf2_sha256$1000$saltsalt", "expected <algorithm>"),
+        ];
+        for (hash, reason) in cases {
+            match parse(hash) {
+                Ok(_) => panic!("{} parsed", hash),
+                Err(e) => assert!(e.contains(reason), "{}: {}", hash, e),
+            }
+            assert!(!Pbkdf2.identify(hash), "{}", hash);
+        }
+        assert!(hash_pbkdf2(b"x", "md5", 1000, None, Format::Phc).is_err());
+        assert!(hash_pbkdf2(b"x", "sha256", 0, None, Format::Phc).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//! Potfile of cracked hashes, one `hash:password` line each as hashcat keeps
+//! it, so a hash is never cracked twice.
+
+use crate::engine::Crack;
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Returns the passwords the potfile at `path` holds for any of `hashes`. A
+/// missing potfile holds nothing.
+pub fn read(path: &Path, hashes: &[String]) -> Result<Vec<Crack>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read potfile {}: {}", path.display(), e)),
+    };
+
+    let mut wanted: HashSet<&str> = hashes.iter().map(String::as_str).collect();
+    let mut cracks = Vec::new();
+    for line in contents.lines() {
+        // Hashes may contain ':' themselves, so try every split point
+        // rather than only the first.
+        let hit = line
+            .match_indices(':')
+            .map(|(i, _)| (&line[..i], &line[i + 1..]))
+            .find(|(hash, _)| wanted.contains(hash));
+        if let Some((hash, password)) = hit {
+            wanted.remove(hash);
+            cracks.push(Crack {
+                hash: hash.to_string(),
+                password: password.to_string(),
+            });
+        }
+    }
+    Ok(cracks)
+}
+
+/// Appends `cracks` to the potfile at `path`, creating it if needed.
+pub fn append(path: &Path, cracks: &[Crack]) -> Result<(), String> {
+    let write = || -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        let lines: String = cracks
+            .iter()
+            .map(|crack| format!("{}:{}\n", crack.hash, crack.password))
+            .collect();
+        file.write_all(lines.as_bytes())
+    };
+    write().map_err(|e| format!("Failed to write potfile {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cracker::display_candidate;
+
+    #[test]
+    fn round_trips_every_password() {
+        let path = std::env::temp_dir().join(format!("potfile-test-{}.pot", std::process::id()));
+        let passwords: &[&[u8]] = &[
+            b"plain",
+            b"with:colon",
+            b"line\nbreak",
+            b"carriage\r",
+            b"tab\there",
+            b"$HEX[70617373]",
+            b"\xff\xfe",
+            b"",
+        ];
+        let cracks: Vec<Crack> = passwords
+            .iter()
+            .enumerate()
+            .map(|(i, password)| Crack {
+                hash: format!("{:032x}", i),
+                password: display_candidate(password),
+            })
+            .collect();
+        let hashes: Vec<String> = cracks.iter().map(|crack| crack.hash.clone()).collect();
+
+        append(&path, &cracks).unwrap();
+        let read = read(&path, &hashes);
+        fs::remove_file(&path).unwrap();
+
+        let read = read.unwrap();
+        assert_eq!(read.len(), cracks.len());
+        for (crack, expected) in read.iter().zip(&cracks) {
+            assert_eq!(crack.hash, expected.hash);
+            assert_eq!(crack.password, expected.password);
+        }
+        assert_eq!(read[0].password, "plain");
+        assert_eq!(read[2].password, "$HEX[6c696e650a627265616b]");
+        assert_eq!(read[5].password, "$HEX[244845585b37303631373337335d]");
+    }
+}
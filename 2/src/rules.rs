@@ -0,0 +1,565 @@
+//! Word mangling rules in hashcat's rule syntax, e.g. `c $1 $2 so0`.
+
+use std::fs;
+use std::path::Path;
+use std::str::Bytes;
+
+/// Candidates grown past this length are rejected, as in hashcat.
+const MAX_RULE_OUTPUT: usize = 256;
+
+#[derive(Clone, Copy)]
+enum Op {
+    Noop,
+    Lower,
+    Upper,
+    Capitalize,
+    InvertCapitalize,
+    ToggleAll,
+    ToggleAt(usize),
+    Reverse,
+    Duplicate,
+    DuplicateN(usize),
+    Reflect,
+    RotateLeft,
+    RotateRight,
+    Append(u8),
+    Prepend(u8),
+    DeleteFirst,
+    DeleteLast,
+    DeleteAt(usize),
+    Extract(usize, usize),
+    Omit(usize, usize),
+    Insert(usize, u8),
+    Overwrite(usize, u8),
+    Truncate(usize),
+    Replace(u8, u8),
+    Purge(u8),
+    DuplicateFirst(usize),
+    DuplicateLast(usize),
+    DuplicateAll,
+    DuplicateBlockFront(usize),
+    DuplicateBlockBack(usize),
+    SwapFront,
+    SwapBack,
+    Swap(usize, usize),
+    ShiftLeft(usize),
+    ShiftRight(usize),
+    Increment(usize),
+    Decrement(usize),
+    ReplaceNext(usize),
+    ReplacePrior(usize),
+    Title,
+    TitleSep(u8),
+    RejectLonger(usize),
+    RejectShorter(usize),
+    RejectUnlessLength(usize),
+    RejectContains(u8),
+    RejectUnlessContains(u8),
+    RejectUnlessFirst(u8),
+    RejectUnlessLast(u8),
+}
+
+/// One line of a rule file: functions applied to a word left to right.
+pub struct Rule {
+    ops: Vec<Op>,
+}
+
+impl Rule {
+    pub fn parse(line: &str) -> Result<Rule, String> {
+        let mut bytes = line.bytes();
+        let mut ops = Vec::new();
+
+        while let Some(f) = bytes.next() {
+            let op = match f {
+                b' ' => continue,
+                b':' => Op::Noop,
+                b'l' => Op::Lower,
+                b'u' => Op::Upper,
+                b'c' => Op::Capitalize,
+                b'C' => Op::InvertCapitalize,
+                b't' => Op::ToggleAll,
+                b'T' => Op::ToggleAt(pos_arg(&mut bytes, f)?),
+                b'r' => Op::Reverse,
+                b'd' => Op::Duplicate,
+                b'p' => Op::DuplicateN(pos_arg(&mut bytes, f)?),
+                b'f' => Op::Reflect,
+                b'{' => Op::RotateLeft,
+                b'}' => Op::RotateRight,
+                b'$' => Op::Append(char_arg(&mut bytes, f)?),
+                b'^' => Op::Prepend(char_arg(&mut bytes, f)?),
+                b'[' => Op::DeleteFirst,
+                b']' => Op::DeleteLast,
+                b'D' => Op::DeleteAt(pos_arg(&mut bytes, f)?),
+                b'x' => Op::Extract(pos_arg(&mut bytes, f)?, pos_arg(&mut bytes, f)?),
+                b'O' => Op::Omit(pos_arg(&mut bytes, f)?, pos_arg(&mut bytes, f)?),
+                b'i' => Op::Insert(pos_arg(&mut bytes, f)?, char_arg(&mut bytes, f)?),
+                b'o' => Op::Overwrite(pos_arg(&mut bytes, f)?, char_arg(&mut bytes, f)?),
+                b'\'' => Op::Truncate(pos_arg(&mut bytes, f)?),
+                b's' => Op::Replace(char_arg(&mut bytes, f)?, char_arg(&mut bytes, f)?),
+                b'@' => Op::Purge(char_arg(&mut bytes, f)?),
+                b'z' => Op::DuplicateFirst(pos_arg(&mut bytes, f)?),
+                b'Z' => Op::DuplicateLast(pos_arg(&mut bytes, f)?),
+                b'q' => Op::DuplicateAll,
+                b'y' => Op::DuplicateBlockFront(pos_arg(&mut bytes, f)?),
+                b'Y' => Op::DuplicateBlockBack(pos_arg(&mut bytes, f)?),
+                b'k' => Op::SwapFront,
+                b'K' => Op::SwapBack,
+                b'*' => Op::Swap(pos_arg(&mut bytes, f)?, pos_arg(&mut bytes, f)?),
+                b'L' => Op::ShiftLeft(pos_arg(&mut bytes, f)?),
+                b'R' => Op::ShiftRight(pos_arg(&mut bytes, f)?),
+                b'+' => Op::Increment(pos_arg(&mut bytes, f)?),
+                b'-' => Op::Decrement(pos_arg(&mut bytes, f)?),
+                b'.' => Op::ReplaceNext(pos_arg(&mut bytes, f)?),
+                b',' => Op::ReplacePrior(pos_arg(&mut bytes, f)?),
+                b'E' => Op::Title,
+                b'e' => Op::TitleSep(char_arg(&mut bytes, f)?),
+                b'<' => Op::RejectLonger(pos_arg(&mut bytes, f)?),
+                b'>' => Op::RejectShorter(pos_arg(&mut bytes, f)?),
+                b'_' => Op::RejectUnlessLength(pos_arg(&mut bytes, f)?),
+                b'!' => Op::RejectContains(char_arg(&mut bytes, f)?),
+                b'/' => Op::RejectUnlessContains(char_arg(&mut bytes, f)?),
+                b'(' => Op::RejectUnlessFirst(char_arg(&mut bytes, f)?),
+                b')' => Op::RejectUnlessLast(char_arg(&mut bytes, f)?),
+                _ => return Err(format!("Unsupported rule function '{}'", f as char)),
+            };
+            ops.push(op);
+        }
+
+        if ops.is_empty() {
+            return Err("Empty rule".to_string());
+        }
+        Ok(Rule { ops })
+    }
+
+    /// Writes `word` mangled by this rule into `out`. Returns `false` when the
+    /// rule rejects the word.
+    pub fn apply(&self, word: &[u8], out: &mut Vec<u8>) -> bool {
+        out.clear();
+        out.extend_from_slice(word);
+
+        for op in &self.ops {
+            let len = out.len();
+            match *op {
+                Op::Noop => {}
+                Op::Lower => out.make_ascii_lowercase(),
+                Op::Upper => out.make_ascii_uppercase(),
+                Op::Capitalize => {
+                    out.make_ascii_lowercase();
+                    if let Some(first) = out.first_mut() {
+                        first.make_ascii_uppercase();
+                    }
+                }
+                Op::InvertCapitalize => {
+                    out.make_ascii_uppercase();
+                    if let Some(first) = out.first_mut() {
+                        first.make_ascii_lowercase();
+                    }
+                }
+                Op::ToggleAll => out.iter_mut().for_each(toggle_case),
+                Op::ToggleAt(n) => {
+                    if let Some(b) = out.get_mut(n) {
+                        toggle_case(b);
+                    }
+                }
+                Op::Reverse => out.reverse(),
+                Op::Duplicate => out.extend_from_within(..),
+                Op::DuplicateN(n) => {
+                    for _ in 0..n {
+                        out.extend_from_within(..len);
+                    }
+                }
+                Op::Reflect => {
+                    out.extend_from_within(..);
+                    out[len..].reverse();
+                }
+                Op::RotateLeft => {
+                    if len > 0 {
+                        out.rotate_left(1);
+                    }
+                }
+                Op::RotateRight => {
+                    if len > 0 {
+                        out.rotate_right(1);
+                    }
+                }
+                Op::Append(c) => out.push(c),
+                Op::Prepend(c) => out.insert(0, c),
+                Op::DeleteFirst => {
+                    if len > 0 {
+                        out.remove(0);
+                    }
+                }
+                Op::DeleteLast => {
+                    out.pop();
+                }
+                Op::DeleteAt(n) => {
+                    if n < len {
+                        out.remove(n);
+                    }
+                }
+                Op::Extract(n, m) => {
+                    if n + m <= len {
+                        out.truncate(n + m);
+                        out.drain(..n);
+                    }
+                }
+                Op::Omit(n, m) => {
+                    if n + m <= len {
+                        out.drain(n..n + m);
+                    }
+                }
+                Op::Insert(n, c) => {
+                    if n <= len {
+                        out.insert(n, c);
+                    }
+                }
+                Op::Overwrite(n, c) => {
+                    if let Some(b) = out.get_mut(n) {
+                        *b = c;
+                    }
+                }
+                Op::Truncate(n) => out.truncate(n),
+                Op::Replace(from, to) => {
+                    out.iter_mut().filter(|b| **b == from).for_each(|b| *b = to)
+                }
+                Op::Purge(c) => out.retain(|&b| b != c),
+                Op::DuplicateFirst(n) => {
+                    if let Some(&first) = out.first() {
+                        out.splice(0..0, std::iter::repeat_n(first, n));
+                    }
+                }
+                Op::DuplicateLast(n) => {
+                    if let Some(&last) = out.last() {
+                        out.extend(std::iter::repeat_n(last, n));
+                    }
+                }
+                Op::DuplicateAll => {
+                    let doubled: Vec<u8> = out.iter().flat_map(|&b| [b, b]).collect();
+                    *out = doubled;
+                }
+                Op::DuplicateBlockFront(n) => {
+                    if n <= len {
+                        let prefix = out[..n].to_vec();
+                        out.splice(0..0, prefix);
+                    }
+                }
+                Op::DuplicateBlockBack(n) => {
+                    if n <= len {
+                        out.extend_from_within(len - n..);
+                    }
+                }
+                Op::SwapFront => {
+                    if len >= 2 {
+                        out.swap(0, 1);
+                    }
+                }
+                Op::SwapBack => {
+                    if len >= 2 {
+                        out.swap(len - 1, len - 2);
+                    }
+                }
+                Op::Swap(n, m) => {
+                    if n < len && m < len {
+                        out.swap(n, m);
+                    }
+                }
+                Op::ShiftLeft(n) => {
+                    if let Some(b) = out.get_mut(n) {
+                        *b <<= 1;
+                    }
+                }
+                Op::ShiftRight(n) => {
+                    if let Some(b) = out.get_mut(n) {
+                        *b >>= 1;
+                    }
+                }
+                Op::Increment(n) => {
+                    if let Some(b) = out.get_mut(n) {
+                        *b = b.wrapping_add(1);
+                    }
+                }
+                Op::Decrement(n) => {
+                    if let Some(b) = out.get_mut(n) {
+                        *b = b.wrapping_sub(1);
+                    }
+                }
+                Op::ReplaceNext(n) => {
+                    if n + 1 < len {
+                        out[n] = out[n + 1];
+                    }
+                }
+                Op::ReplacePrior(n) => {
+                    if n >= 1 && n < len {
+                        out[n] = out[n - 1];
+                    }
+                }
+                Op::Title => title_case(out, b' '),
+                Op::TitleSep(sep) => title_case(out, sep),
+                Op::RejectLonger(n) => {
+                    if len > n {
+                        return false;
+                    }
+                }
+                Op::RejectShorter(n) => {
+                    if len < n {
+                        return false;
+                    }
+                }
+                Op::RejectUnlessLength(n) => {
+                    if len != n {
+                        return false;
+                    }
+                }
+                Op::RejectContains(c) => {
+                    if out.contains(&c) {
+                        return false;
+                    }
+                }
+                Op::RejectUnlessContains(c) => {
+                    if !out.contains(&c) {
+                        return false;
+                    }
+                }
+                Op::RejectUnlessFirst(c) => {
+                    if out.first() != Some(&c) {
+                        return false;
+                    }
+                }
+                Op::RejectUnlessLast(c) => {
+                    if out.last() != Some(&c) {
+                        return false;
+                    }
+                }
+            }
+            if out.len() > MAX_RULE_OUTPUT {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn char_arg(bytes: &mut Bytes, f: u8) -> Result<u8, String> {
+    bytes
+        .next()
+        .ok_or_else(|| format!("Missing argument for '{}'", f as char))
+}
+
+/// Positions are written as `0-9` then `A-Z` for 10-35.
+fn pos_arg(bytes: &mut Bytes, f: u8) -> Result<usize, String> {
+    match bytes.next() {
+        Some(b @ b'0'..=b'9') => Ok((b - b'0') as usize),
+        Some(b @ b'A'..=b'Z') => Ok((b - b'A') as usize + 10),
+        Some(b) => Err(format!(
+            "Invalid position '{}' for '{}'",
+            b as char, f as char
+        )),
+        None => Err(format!("Missing position for '{}'", f as char)),
+    }
+}
+
+fn toggle_case(b: &mut u8) {
+    if b.is_ascii_lowercase() {
+        b.make_ascii_uppercase();
+    } else if b.is_ascii_uppercase() {
+        b.make_ascii_lowercase();
+    }
+}
+
+fn title_case(out: &mut [u8], sep: u8) {
+    out.make_ascii_lowercase();
+    let mut upper_next = true;
+    for b in out.iter_mut() {
+        if upper_next {
+            b.make_ascii_uppercase();
+        }
+        upper_next = *b == sep;
+    }
+}
+
+/// Rules loaded from a rule file, applied to every word of a wordlist.
+pub struct RuleSet {
+    rules: Vec<Rule>,
+    skipped: Vec<String>,
+}
+
+impl RuleSet {
+    /// Loads a hashcat rule file. Comments and blank lines are ignored, and
+    /// rules using unsupported functions are skipped.
+    pub fn load(path: &Path) -> Result<RuleSet, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        let mut rules = Vec::new();
+        let mut skipped = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match Rule::parse(line) {
+                Ok(rule) => rules.push(rule),
+                Err(e) => skipped.push(format!("Skipping rule on line {}: {}", i + 1, e)),
+            }
+        }
+        if rules.is_empty() {
+            return Err(format!("No usable rules in {}", path.display()));
+        }
+        Ok(RuleSet { rules, skipped })
+    }
+
+    /// Why rules of the file were skipped, one message per rule.
+    pub fn skipped(&self) -> &[String] {
+        &self.skipped
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Calls `f` with every candidate the rules produce from `word`, reusing
+    /// `buf` so no candidate is allocated.
+    pub fn for_each_candidate(&self, word: &[u8], buf: &mut Vec<u8>, mut f: impl FnMut(&[u8])) {
+        for rule in &self.rules {
+            if rule.apply(word, buf) {
+                f(buf);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(rule: &str, word: &str) -> Option<Vec<u8>> {
+        let rule = Rule::parse(rule).unwrap_or_else(|e| panic!("{:?}: {}", rule, e));
+        let mut out = Vec::new();
+        rule.apply(word.as_bytes(), &mut out).then_some(out)
+    }
+
+    #[test]
+    fn applies_every_function() {
+        let cases: &[(&str, &str, Option<&[u8]>)] = &[
+            (":", "pass", Some(b"pass")),
+            ("l", "PaSs", Some(b"pass")),
+            ("u", "PaSs", Some(b"PASS")),
+            ("c", "pASS", Some(b"Pass")),
+            ("C", "pass", Some(b"pASS")),
+            ("t", "PaSs1", Some(b"pAsS1")),
+            ("T0", "pass", Some(b"Pass")),
+            ("T9", "pass", Some(b"pass")),
+            ("r", "abc", Some(b"cba")),
+            ("d", "abc", Some(b"abcabc")),
+            ("p2", "ab", Some(b"ababab")),
+            ("f", "abc", Some(b"abccba")),
+            ("{", "abc", Some(b"bca")),
+            ("}", "abc", Some(b"cab")),
+            ("{", "", Some(b"")),
+            ("$1", "abc", Some(b"abc1")),
+            ("^1", "abc", Some(b"1abc")),
+            ("[", "abc", Some(b"bc")),
+            ("]", "abc", Some(b"ab")),
+            ("[", "", Some(b"")),
+            ("D1", "abc", Some(b"ac")),
+            ("D5", "abc", Some(b"abc")),
+            ("x12", "abcde", Some(b"bc")),
+            ("x35", "abcde", Some(b"abcde")),
+            ("O12", "abcde", Some(b"ade")),
+            ("O45", "abcde", Some(b"abcde")),
+            ("i1!", "abc", Some(b"a!bc")),
+            ("i3!", "abc", Some(b"abc!")),
+            ("i5!", "abc", Some(b"abc")),
+            ("o1!", "abc", Some(b"a!c")),
+            ("o3!", "abc", Some(b"abc")),
+            ("'2", "abcde", Some(b"ab")),
+            ("'9", "abcde", Some(b"abcde")),
+            ("ss$", "pass", Some(b"pa$$")),
+            ("@s", "pass", Some(b"pa")),
+            ("z2", "abc", Some(b"aaabc")),
+            ("Z2", "abc", Some(b"abccc")),
+            ("z2", "", Some(b"")),
+            ("q", "abc", Some(b"aabbcc")),
+            ("y2", "abc", Some(b"ababc")),
+            ("y5", "abc", Some(b"abc")),
+            ("Y2", "abc", Some(b"abcbc")),
+            ("Y5", "abc", Some(b"abc")),
+            ("k", "abc", Some(b"bac")),
+            ("K", "abc", Some(b"acb")),
+            ("k", "a", Some(b"a")),
+            ("*02", "abc", Some(b"cba")),
+            ("*09", "abc", Some(b"abc")),
+            ("L0", "abc", Some(&[0xc2, b'b', b'c'])),
+            ("R0", "abc", Some(b"0bc")),
+            ("L9", "abc", Some(b"abc")),
+            ("+0", "abc", Some(b"bbc")),
+            ("-1", "abc", Some(b"aac")),
+            ("+9", "abc", Some(b"abc")),
+            (".0", "abc", Some(b"bbc")),
+            (".2", "abc", Some(b"abc")),
+            (",1", "abc", Some(b"aac")),
+            (",0", "abc", Some(b"abc")),
+            ("E", "hello WORLD", Some(b"Hello World")),
+            ("e-", "foo-BAR", Some(b"Foo-Bar")),
+            ("<4", "abcd", Some(b"abcd")),
+            ("<3", "abcd", None),
+            (">4", "abcd", Some(b"abcd")),
+            (">5", "abcd", None),
+            ("_4", "abcd", Some(b"abcd")),
+            ("_3", "abcd", None),
+            ("!z", "abc", Some(b"abc")),
+            ("!a", "abc", None),
+            ("/a", "abc", Some(b"abc")),
+            ("/z", "abc", None),
+            ("(a", "abc", Some(b"abc")),
+            ("(b", "abc", None),
+            (")c", "abc", Some(b"abc")),
+            (")b", "abc", None),
+            // Positions past 9 are letters.
+            ("TA", "abcdefghijk", Some(b"abcdefghijK")),
+            // Lines of best64.rule.
+            ("$1 $2 $3", "password", Some(b"password123")),
+            ("$e $s", "pass", Some(b"passes")),
+            ("] ] ]", "password", Some(b"passw")),
+            ("x04", "password", Some(b"pass")),
+            ("o0d", "password", Some(b"dassword")),
+            ("i61", "password", Some(b"passwo1rd")),
+            ("} } } } '4", "password", Some(b"word")),
+            ("+5 ] } } } } '4", "password", Some(b"swpr")),
+            // Words grown past the longest candidate are rejected.
+            ("d d d d d d", "password", None),
+        ];
+        for &(rule, word, expected) in cases {
+            assert_eq!(
+                apply(rule, word).as_deref(),
+                expected,
+                "rule {:?} on {:?}",
+                rule,
+                word
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_functions() {
+        for rule in ["=1a", "%2a", "M", "4", "6", "X012", "Q", "31-", "c M"] {
+            let e = Rule::parse(rule).err();
+            assert!(
+                e.as_deref().is_some_and(|e| e.starts_with("Unsupported")),
+                "rule {:?} gave {:?}",
+                rule,
+                e
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_arguments() {
+        for rule in ["", " ", "$", "sa", "Ta", "T", "x1", "i1"] {
+            assert!(Rule::parse(rule).is_err(), "rule {:?}", rule);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::algorithm::HashOptions;
+use crate::engine::{CandidateSource, Crack};
+use crate::keyspace::Partition;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often a running search writes its session file.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How far a search has got. Everything before the position has been checked.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Position {
+    /// Every candidate shorter than `length` and the first `offset`
+    /// candidates of `length`.
+    Keyspace { length: usize, offset: u64 },
+    /// The first `words` lines of the wordlist, `bytes` bytes long.
+    Wordlist { bytes: u64, words: u64 },
+}
+
+/// Everything needed to continue an interrupted search.
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub algo: String,
+    #[serde(default)]
+    pub options: HashOptions,
+    /// Whether the search used an Argon2 secret, which the session does not
+    /// store and the restore has to be given again.
+    #[serde(default)]
+    pub uses_secret: bool,
+    /// Hashes not cracked yet.
+    pub hashes: Vec<String>,
+    pub found: Vec<Crack>,
+    pub total_targets: usize,
+    pub source: CandidateSource,
+    pub partition: Partition,
+    pub position: Option<Position>,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Session, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read session {}: {}", path.display(), e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid session file {}: {}", path.display(), e))
+    }
+
+    /// Writes the session next to `path` first and renames it into place,
+    /// so a crash mid-write never leaves a truncated session behind. The
+    /// file holds the passwords found, so only its owner may read it.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        write_private(Path::new(&tmp), json.as_bytes())
+            .and_then(|()| fs::rename(&tmp, path))
+            .map_err(|e| format!("Failed to write session {}: {}", path.display(), e))
+    }
+}
+
+/// Writes `contents` to a new file at `path` that only its owner can read
+/// and write, replacing any file already there.
+fn write_private(path: &Path, contents: &[u8]) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(contents)
+}
+
+/// Session file a running search checkpoints to.
+pub(crate) struct SessionFile {
+    pub path: PathBuf,
+    pub source: CandidateSource,
+    pub partition: Partition,
+    periodic: bool,
+    written: bool,
+    last_saved: Instant,
+}
+
+impl SessionFile {
+    /// Without `periodic` the file is only written when the job stops.
+    pub fn new(
+        path: PathBuf,
+        source: CandidateSource,
+        partition: Partition,
+        periodic: bool,
+    ) -> Self {
+        SessionFile {
+            path,
+            source,
+            partition,
+            periodic,
+            written: false,
+            last_saved: Instant::now(),
+        }
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.periodic && self.last_saved.elapsed() >= CHECKPOINT_INTERVAL
+    }
+
+    pub fn save(&mut self, session: &Session) -> Result<(), String> {
+        self.last_saved = Instant::now();
+        session.save(&self.path)?;
+        self.written = true;
+        Ok(())
+    }
+
+    /// Whether the file belongs to this job: it was restored from or
+    /// checkpointed to, rather than left over from another job.
+    pub fn is_owned(&self) -> bool {
+        self.periodic || self.written
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn session(secret: Option<&[u8]>) -> Session {
+        Session {
+            algo: "argon2".to_string(),
+            options: HashOptions {
+                secret: secret.map(<[u8]>::to_vec),
+                ..HashOptions::default()
+            },
+            uses_secret: secret.is_some(),
+            hashes: vec![
+                "$argon2id$v=19$m=65536,t=2,p=1$c29tZXNhbHQ$CTFhFdXPJO1aFaMaO6Mm5c8y7cJHAph8ArZWb2GRPPc"
+                    .to_string(),
+            ],
+            found: Vec::new(),
+            total_targets: 1,
+            source: CandidateSource::Brute {
+                charset: "abc".to_string(),
+                min_len: 1,
+                max_len: 2,
+            },
+            partition: Partition::default(),
+            position: None,
+        }
+    }
+
+    #[test]
+    fn saves_no_secret() {
+        let path = std::env::temp_dir().join(format!("session-test-{}", std::process::id()));
+        // A file left with wider permissions is replaced, not reused.
+        fs::write(&path, "").unwrap();
+        let saved = session(Some(b"pepper")).save(&path);
+        let contents = fs::read_to_string(&path);
+        let loaded = Session::load(&path);
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            fs::metadata(&path).unwrap().permissions().mode()
+        };
+        fs::remove_file(&path).unwrap();
+
+        saved.unwrap();
+        assert!(!contents.unwrap().contains("\"secret\""));
+        let loaded = loaded.unwrap();
+        assert!(loaded.uses_secret);
+        assert!(loaded.options.secret.is_none());
+        #[cfg(unix)]
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -1,7 +1,9 @@
-use sha1::{Digest, Sha1};
+use crate::algorithm::{DigestAlgorithm, hash_with};
 
-pub fn hash_sha1(input: &str) -> String {
-    let mut hasher = Sha1::new();
-    hasher.update(input.as_bytes());
-    hex::encode(hasher.finalize())
-}
+pub static SHA1: DigestAlgorithm = DigestAlgorithm {
+    name: "sha1",
+    label: "SHA-1",
+    len: 20,
+    hash: hash_with::<sha1::Sha1>,
+    detect: true,
+};
@@ -0,0 +1,118 @@
+//! The SHA-2 family: SHA-224, SHA-256, SHA-384, SHA-512 and the truncated
+//! SHA-512/224 and SHA-512/256, all unsalted.
+
+use crate::algorithm::{DigestAlgorithm, hash_with};
+
+pub static SHA224: DigestAlgorithm = DigestAlgorithm {
+    name: "sha224",
+    label: "SHA-224",
+    len: 28,
+    hash: hash_with::<sha2::Sha224>,
+    detect: true,
+};
+
+pub static SHA256: DigestAlgorithm = DigestAlgorithm {
+    name: "sha256",
+    label: "SHA-256",
+    len: 32,
+    hash: hash_with::<sha2::Sha256>,
+    detect: true,
+};
+
+pub static SHA384: DigestAlgorithm = DigestAlgorithm {
+    name: "sha384",
+    label: "SHA-384",
+    len: 48,
+    hash: hash_with::<sha2::Sha384>,
+    detect: true,
+};
+
+pub static SHA512: DigestAlgorithm = DigestAlgorithm {
+    name: "sha512",
+    label: "SHA-512",
+    len: 64,
+    hash: hash_with::<sha2::Sha512>,
+    detect: true,
+};
+
+/// Looks exactly like SHA-224, so only `--algo` selects it.
+pub static SHA512_224: DigestAlgorithm = DigestAlgorithm {
+    name: "sha512-224",
+    label: "SHA-512/224",
+    len: 28,
+    hash: hash_with::<sha2::Sha512_224>,
+    detect: false,
+};
+
+/// Looks exactly like SHA-256, so only `--algo` selects it.
+pub static SHA512_256: DigestAlgorithm = DigestAlgorithm {
+    name: "sha512-256",
+    label: "SHA-512/256",
+    len: 32,
+    hash: hash_with::<sha2::Sha512_256>,
+    detect: false,
+};
+
+#[cfg(test)]
+mod tests {
+    use crate::algorithm::{self, HashOptions};
+
+    /// Digests of `""` and `"abc"` from FIPS 180-4.
+    const VECTORS: &[(&str, &str, &str)] = &[
+        (
+            "sha224",
+            "d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f",
+            "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7",
+        ),
+        (
+            "sha256",
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        ),
+        (
+            "sha384",
+            "38b060a751ac96384cd9327eb1b1e36a21fdb71114be07434c0cc7bf63f6e1da274edebfe76f65fbd51ad2f14898b95b",
+            "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7",
+        ),
+        (
+            "sha512",
+            "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e",
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+        ),
+        (
+            "sha512-224",
+            "6ed0dd02806fa89e25de060c19d3ac86cabb87d6a0ddd05c333b84f4",
+            "4634270f707b6a54daae7530460842e20e37ed265ceee9a43e8924aa",
+        ),
+        (
+            "sha512-256",
+            "c672b8d1ef56ed28ab87c3622c5114069bdd3ad7b8f9737498d0c01ecef0967a",
+            "53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23",
+        ),
+    ];
+
+    #[test]
+    fn hashes_known_vectors() {
+        for &(name, empty, abc) in VECTORS {
+            let algo = algorithm::find(name).unwrap();
+            for (input, expected) in [(&b""[..], empty), (b"abc", abc)] {
+                let digest = algo.digest(input).unwrap();
+                assert_eq!(hex::encode(digest.as_bytes()), expected, "{}", name);
+                let target = algo.prepare(expected, &HashOptions::default()).unwrap();
+                assert!(target.check(input), "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn detects_only_the_untruncated_digests() {
+        let sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let names: Vec<&str> = algorithm::ALGORITHMS
+            .iter()
+            .filter(|algo| algo.identify(sha256))
+            .map(|algo| algo.name())
+            .collect();
+        assert_eq!(names, ["sha256"]);
+        assert!(algorithm::find("sha512-256").unwrap().matches_shape(sha256));
+    }
+}
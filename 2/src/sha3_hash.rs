@@ -0,0 +1,110 @@
+//! SHA-3 and Keccak, the same sponge with different padding: Keccak is the
+//! submission as it stood before standardisation, still used by Ethereum
+//! among others, and its digests differ from SHA-3's for every input.
+//!
+//! Their digests look like SHA-2 ones of the same length, so only `--algo`
+//! selects them.
+
+use crate::algorithm::{DigestAlgorithm, hash_with};
+
+pub static SHA3_224: DigestAlgorithm = DigestAlgorithm {
+    name: "sha3-224",
+    label: "SHA3-224",
+    len: 28,
+    hash: hash_with::<sha3::Sha3_224>,
+    detect: false,
+};
+
+pub static SHA3_256: DigestAlgorithm = DigestAlgorithm {
+    name: "sha3-256",
+    label: "SHA3-256",
+    len: 32,
+    hash: hash_with::<sha3::Sha3_256>,
+    detect: false,
+};
+
+pub static SHA3_384: DigestAlgorithm = DigestAlgorithm {
+    name: "sha3-384",
+    label: "SHA3-384",
+    len: 48,
+    hash: hash_with::<sha3::Sha3_384>,
+    detect: false,
+};
+
+pub static SHA3_512: DigestAlgorithm = DigestAlgorithm {
+    name: "sha3-512",
+    label: "SHA3-512",
+    len: 64,
+    hash: hash_with::<sha3::Sha3_512>,
+    detect: false,
+};
+
+pub static KECCAK256: DigestAlgorithm = DigestAlgorithm {
+    name: "keccak-256",
+    label: "Keccak-256",
+    len: 32,
+    hash: hash_with::<sha3::Keccak256>,
+    detect: false,
+};
+
+pub static KECCAK512: DigestAlgorithm = DigestAlgorithm {
+    name: "keccak-512",
+    label: "Keccak-512",
+    len: 64,
+    hash: hash_with::<sha3::Keccak512>,
+    detect: false,
+};
+
+#[cfg(test)]
+mod tests {
+    use crate::algorithm::{self, HashOptions};
+
+    /// Digests of `""` and `"abc"`, from FIPS 202 for SHA-3 and the Keccak
+    /// team's reference for Keccak. Keccak's padding gives different
+    /// digests for the same input.
+    const VECTORS: &[(&str, &str, &str)] = &[
+        (
+            "sha3-224",
+            "6b4e03423667dbb73b6e15454f0eb1abd4597f9a1b078e3f5b5a6bc7",
+            "e642824c3f8cf24ad09234ee7d3c766fc9a3a5168d0c94ad73b46fdf",
+        ),
+        (
+            "sha3-256",
+            "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a",
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+        ),
+        (
+            "sha3-384",
+            "0c63a75b845e4f7d01107d852e4c2485c51a50aaaa94fc61995e71bbee983a2ac3713831264adb47fb6bd1e058d5f004",
+            "ec01498288516fc926459f58e2c6ad8df9b473cb0fc08c2596da7cf0e49be4b298d88cea927ac7f539f1edf228376d25",
+        ),
+        (
+            "sha3-512",
+            "a69f73cca23a9ac5c8b567dc185a756e97c982164fe25859e0d1dcc1475c80a615b2123af1f5f94c11e3e9402c3ac558f500199d95b6d3e301758586281dcd26",
+            "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0",
+        ),
+        (
+            "keccak-256",
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45",
+        ),
+        (
+            "keccak-512",
+            "0eab42de4c3ceb9235fc91acffe746b29c29a8c366b7c60e4e67c466f36a4304c00fa9caf9d87976ba469bcbe06713b435f091ef2769fb160cdab33d3670680e",
+            "18587dc2ea106b9a1563e32b3312421ca164c7f1f07bc922a9c83d77cea3a1e5d0c69910739025372dc14ac9642629379540c17e2a65b19d77aa511a9d00bb96",
+        ),
+    ];
+
+    #[test]
+    fn hashes_known_vectors() {
+        for &(name, empty, abc) in VECTORS {
+            let algo = algorithm::find(name).unwrap();
+            for (input, expected) in [(&b""[..], empty), (b"abc", abc)] {
+                let digest = algo.digest(input).unwrap();
+                assert_eq!(hex::encode(digest.as_bytes()), expected, "{}", name);
+                let target = algo.prepare(expected, &HashOptions::default()).unwrap();
+                assert!(target.check(input), "{}", name);
+            }
+        }
+    }
+}
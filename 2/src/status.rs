@@ -0,0 +1,283 @@
+//! Status display of a running job, printed on a timer and on request, from
+//! the job's live counters rather than its batches, with the progress and
+//! speed of each length, and the keys that control the job.
+
+use hash_brute_force::{CancelToken, LengthStats, Monitor, Snapshot};
+use std::io::{self, BufRead, Read};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Shortest window the speed is measured over; refreshes closer together
+/// than that report the average since the start instead.
+const MIN_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+enum Message {
+    Refresh,
+    Stop,
+}
+
+pub struct StatusReporter {
+    sender: Sender<Message>,
+    handle: JoinHandle<()>,
+}
+
+impl StatusReporter {
+    /// Prints the status every `interval`. With `keys`, keys pressed on the
+    /// terminal control the job, see [`command`]; they act at once where the
+    /// terminal can be switched to deliver single keys, after Enter
+    /// otherwise. [`StatusReporter::stop`] or [`restore_terminal`] switch it
+    /// back.
+    pub fn start(monitor: Monitor, interval: Option<Duration>, keys: Option<CancelToken>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        if let Some(control) = keys {
+            let sender = sender.clone();
+            let single_keys = terminal::read_single_keys();
+            // Left blocked on stdin when the job ends; it goes with the
+            // process.
+            thread::spawn(move || {
+                if single_keys {
+                    read_keys(&control, &sender);
+                } else {
+                    read_lines(&control, &sender);
+                }
+            });
+        }
+
+        let handle = thread::spawn(move || {
+            let first = monitor.snapshot();
+            let mut last = first.clone();
+            loop {
+                let message = match interval {
+                    Some(interval) => receiver.recv_timeout(interval),
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                match message {
+                    Ok(Message::Refresh) | Err(RecvTimeoutError::Timeout) => {}
+                    Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+                }
+                let now = monitor.snapshot();
+                print_status(&first, &last, &now);
+                last = now;
+            }
+        });
+
+        StatusReporter { sender, handle }
+    }
+
+    pub fn stop(self) {
+        restore_terminal();
+        let _ = self.sender.send(Message::Stop);
+        let _ = self.handle.join();
+    }
+}
+
+/// Puts the terminal back the way it was before keys were read, for exits
+/// that skip [`StatusReporter::stop`].
+pub fn restore_terminal() {
+    terminal::restore();
+}
+
+/// Runs the command of `key`: `s` (or Enter) prints the status, `p` pauses,
+/// `r` resumes and `q` stops the job with a checkpoint. Returns `false` once
+/// the status display is gone.
+fn command(key: char, control: &CancelToken, sender: &Sender<Message>, help: &str) -> bool {
+    match key {
+        '\n' | 's' => return sender.send(Message::Refresh).is_ok(),
+        'p' => {
+            println!("Pausing after the current batch");
+            control.pause();
+        }
+        'r' => control.resume(),
+        'q' => {
+            println!("Stopping after the current batch");
+            control.cancel();
+        }
+        _ => println!("{}", help),
+    }
+    true
+}
+
+/// Reads a command per key press.
+fn read_keys(control: &CancelToken, sender: &Sender<Message>) {
+    for byte in io::stdin().lock().bytes() {
+        let Ok(byte) = byte else {
+            break;
+        };
+        let help = "Keys: s = status, p = pause, r = resume, q = quit";
+        if byte != b'\r' && !command(byte as char, control, sender, help) {
+            break;
+        }
+    }
+}
+
+/// Reads a command per line, for terminals that deliver whole lines.
+fn read_lines(control: &CancelToken, sender: &Sender<Message>) {
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let mut chars = line.trim().chars();
+        let key = match (chars.next(), chars.next()) {
+            (None, _) => '\n',
+            (Some(key), None) => key,
+            _ => '?',
+        };
+        let help = "Keys: s = status, p = pause, r = resume, q = quit (then Enter)";
+        if !command(key, control, sender, help) {
+            break;
+        }
+    }
+}
+
+/// Switching the terminal to deliver each key as it is pressed.
+#[cfg(unix)]
+mod terminal {
+    use nix::sys::termios::{self, LocalFlags, SetArg, SpecialCharacterIndices, Termios};
+    use std::io;
+    use std::sync::Mutex;
+
+    /// Settings of the terminal before [`read_single_keys`] changed them.
+    static SAVED: Mutex<Option<Termios>> = Mutex::new(None);
+
+    /// Turns off line editing and echo, so reads return each key as it is
+    /// pressed. Unlike full raw mode, Ctrl-C still interrupts and output
+    /// still ends lines as before. Returns whether the terminal allowed it.
+    pub fn read_single_keys() -> bool {
+        let stdin = io::stdin();
+        let Ok(saved) = termios::tcgetattr(&stdin) else {
+            return false;
+        };
+        let mut keys = saved.clone();
+        keys.local_flags
+            .remove(LocalFlags::ICANON | LocalFlags::ECHO);
+        keys.control_chars[SpecialCharacterIndices::VMIN as usize] = 1;
+        keys.control_chars[SpecialCharacterIndices::VTIME as usize] = 0;
+        if termios::tcsetattr(&stdin, SetArg::TCSANOW, &keys).is_err() {
+            return false;
+        }
+        *SAVED.lock().unwrap() = Some(saved);
+        true
+    }
+
+    pub fn restore() {
+        if let Some(saved) = SAVED.lock().unwrap().take() {
+            let _ = termios::tcsetattr(io::stdin(), SetArg::TCSANOW, &saved);
+        }
+    }
+}
+
+/// Other systems keep reading whole lines.
+#[cfg(not(unix))]
+mod terminal {
+    pub fn read_single_keys() -> bool {
+        false
+    }
+
+    pub fn restore() {}
+}
+
+fn print_status(first: &Snapshot, last: &Snapshot, now: &Snapshot) {
+    let since = if now.elapsed - last.elapsed >= MIN_RATE_WINDOW {
+        last
+    } else {
+        first
+    };
+    let window = (now.elapsed - since.elapsed).as_secs_f64();
+    let rate = (window > 0.0).then(|| now.done.saturating_sub(since.done) as f64 / window);
+
+    let mut status = match (now.total, now.fraction) {
+        (Some(total), Some(fraction)) => format!(
+            "  Status:   {}/{} ({:.1}%)",
+            now.done,
+            total,
+            fraction * 100.0
+        ),
+        (None, Some(fraction)) => format!("  Status:   {} ({:.1}%)", now.done, fraction * 100.0),
+        _ => format!("  Status:   {}", now.done),
+    };
+    if let Some(length) = now.length {
+        status += &format!(", length {}", length);
+    }
+
+    let eta = match (now.total, now.fraction, since.fraction) {
+        (Some(total), _, _) => rate
+            .filter(|&rate| rate > 0.0)
+            .map(|rate| (total - now.done.min(total)) as f64 / rate),
+        (None, Some(now_fraction), Some(since_fraction)) if now_fraction > since_fraction => {
+            Some((1.0 - now_fraction) * window / (now_fraction - since_fraction))
+        }
+        _ => None,
+    };
+    let speed = format!(
+        "  Speed:    {} candidates/s, ETA {}",
+        rate.map_or("-".to_string(), format_rate),
+        eta.and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .map_or("unknown".to_string(), format_duration)
+    );
+
+    let mut lines = vec![status, speed];
+    for length in &now.completed_lengths {
+        lines.push(format!(
+            "  Length {}: {} in {}, {} candidates/s",
+            length.length,
+            length.done,
+            format_duration(length.elapsed),
+            length_rate(length)
+        ));
+    }
+    if let Some(length) = &now.current_length {
+        let fraction = match length.total {
+            0 => 1.0,
+            total => length.done as f64 / total as f64,
+        };
+        lines.push(format!(
+            "  Length {}: {}/{} ({:.1}%), {} candidates/s",
+            length.length,
+            length.done,
+            length.total,
+            fraction * 100.0,
+            length_rate(length)
+        ));
+    }
+    if let Some(current) = &now.current {
+        lines.push(format!("  Current:  {}", current));
+    }
+    lines.push(format!(
+        "  Cracked:  {}/{} - {}",
+        now.cracked,
+        now.targets,
+        format_duration(now.elapsed)
+    ));
+    println!("{}", lines.join("\n"));
+}
+
+/// Average speed over a length so far.
+fn length_rate(length: &LengthStats) -> String {
+    let secs = length.elapsed.as_secs_f64();
+    if secs > 0.0 {
+        format_rate(length.done as f64 / secs)
+    } else {
+        "-".to_string()
+    }
+}
+
+fn format_rate(rate: f64) -> String {
+    match rate {
+        r if r >= 1e9 => format!("{:.2}G", r / 1e9),
+        r if r >= 1e6 => format!("{:.2}M", r / 1e6),
+        r if r >= 1e3 => format!("{:.2}k", r / 1e3),
+        r => format!("{:.1}", r),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+        3600..86400 => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d {:02}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
@@ -0,0 +1,105 @@
+use crate::algorithm::{HashAlgorithm, HashOptions, Target};
+use std::collections::{HashMap, HashSet};
+
+/// The hashes still to be cracked in a run, all of the same algorithm.
+pub struct TargetSet {
+    algo: &'static dyn HashAlgorithm,
+    options: HashOptions,
+    /// Unsalted targets keyed by digest, so each candidate is hashed once and
+    /// looked up instead of compared against every target.
+    digests: HashMap<Vec<u8>, String>,
+    /// Salted targets, each verifying the candidate on its own.
+    verifiers: Vec<(String, Box<dyn Target>)>,
+}
+
+impl TargetSet {
+    pub fn new(algo: &'static dyn HashAlgorithm) -> Self {
+        Self::with_options(algo, HashOptions::default())
+    }
+
+    /// A set whose hashes are all parsed and checked with `options`.
+    pub fn with_options(algo: &'static dyn HashAlgorithm, options: HashOptions) -> Self {
+        TargetSet {
+            algo,
+            options,
+            digests: HashMap::new(),
+            verifiers: Vec::new(),
+        }
+    }
+
+    /// Adds `hash` to the set; duplicates are ignored.
+    pub fn insert(&mut self, hash: &str) -> Result<(), String> {
+        let target = self.algo.prepare(hash, &self.options)?;
+        match target.digest() {
+            Some(digest) => {
+                self.digests
+                    .entry(digest.to_vec())
+                    .or_insert_with(|| hash.to_string());
+            }
+            None => {
+                if !self.verifiers.iter().any(|(h, _)| h == hash) {
+                    self.verifiers.push((hash.to_string(), target));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn algorithm(&self) -> &'static dyn HashAlgorithm {
+        self.algo
+    }
+
+    pub fn options(&self) -> &HashOptions {
+        &self.options
+    }
+
+    /// The hashes still in the set, as they were inserted.
+    pub fn hashes(&self) -> Vec<String> {
+        self.digests
+            .values()
+            .chain(self.verifiers.iter().map(|(hash, _)| hash))
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.digests.len() + self.verifiers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes of working memory a worker needs to check a candidate against
+    /// any target of the set.
+    pub fn memory_per_check(&self) -> u64 {
+        self.verifiers
+            .iter()
+            .map(|(_, target)| target.memory())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the hashes in the set that `candidate` is a password for.
+    pub fn matches(&self, candidate: &[u8]) -> Vec<&str> {
+        let mut hits = Vec::new();
+        if !self.digests.is_empty()
+            && let Some(digest) = self.algo.digest(candidate)
+            && let Some(hash) = self.digests.get(digest.as_bytes())
+        {
+            hits.push(hash.as_str());
+        }
+        for (hash, target) in &self.verifiers {
+            if target.check(candidate) {
+                hits.push(hash.as_str());
+            }
+        }
+        hits
+    }
+
+    /// Drops cracked hashes from the working set.
+    pub fn remove(&mut self, cracked: &HashSet<String>) {
+        self.digests.retain(|_, hash| !cracked.contains(hash));
+        self.verifiers.retain(|(hash, _)| !cracked.contains(hash));
+    }
+}
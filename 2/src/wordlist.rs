@@ -0,0 +1,141 @@
+use crate::batch::{BatchSizer, TARGET_BATCH_TIME};
+use crate::cracker::{self, Cracker};
+use crate::engine::Event;
+use crate::rules::RuleSet;
+use crate::session::Position;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+use std::time::Instant;
+
+/// Most candidates checked in parallel at a time, which bounds the words held
+/// in memory; with rules, each word counts once per rule.
+const MAX_BATCH_CANDIDATES: u64 = 1_000_000;
+
+/// Checks every line of the wordlist (`-` for stdin), mangled by every rule
+/// when rules are given, without loading the whole file into memory.
+pub fn wordlist_attack(
+    cracker: &mut Cracker,
+    path: &Path,
+    rules: Option<&RuleSet>,
+) -> io::Result<()> {
+    let (bytes_done, words_done) = match cracker.take_resume() {
+        Some(Position::Wordlist { bytes, words }) => (bytes, words),
+        _ => (0, 0),
+    };
+
+    let (mut reader, total_bytes): (Box<dyn BufRead>, Option<u64>) = if path == Path::new("-") {
+        let mut stdin = io::stdin().lock();
+        // Stdin cannot seek, so words checked before a restore are read again
+        // and dropped.
+        let mut skipped = Vec::new();
+        for _ in 0..words_done {
+            skipped.clear();
+            if stdin.read_until(b'\n', &mut skipped)? == 0 {
+                break;
+            }
+        }
+        (Box::new(stdin), None)
+    } else {
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        file.seek(SeekFrom::Start(bytes_done))?;
+        (Box::new(BufReader::with_capacity(1 << 20, file)), Some(len))
+    };
+
+    cracker.emit(Event::WordlistStarted {
+        path,
+        rules: rules.map(RuleSet::len),
+        resumed_words: words_done,
+    });
+    let rule_count = rules.map_or(1, RuleSet::len) as u64;
+    let mut sizer = BatchSizer::new(TARGET_BATCH_TIME, MAX_BATCH_CANDIDATES, cracker.threads());
+    let monitor = cracker.monitor();
+    monitor.set_total(None);
+    monitor.set_done(words_done * rule_count);
+
+    let mut buf = Vec::new();
+    let mut words: Vec<Range<usize>> = Vec::new();
+    let mut bytes_read = bytes_done;
+    let mut words_read = words_done;
+
+    loop {
+        let batch_words = (sizer.size() / rule_count).max(1) as usize;
+        buf.clear();
+        words.clear();
+        while words.len() < batch_words {
+            let start = buf.len();
+            let n = reader.read_until(b'\n', &mut buf)?;
+            if n == 0 {
+                break;
+            }
+            bytes_read += n as u64;
+            let mut end = buf.len();
+            while end > start && matches!(buf[end - 1], b'\n' | b'\r') {
+                end -= 1;
+            }
+            words.push(start..end);
+        }
+        if words.is_empty() {
+            break;
+        }
+        words_read += words.len() as u64;
+        let batch_start = Instant::now();
+
+        let targets = cracker.targets();
+        let monitor = cracker.monitor();
+        let hits = cracker.install(|| match rules {
+            None => words
+                .par_iter()
+                .map_init(
+                    || monitor.counter(),
+                    |counter, word| {
+                        let word = &buf[word.clone()];
+                        counter.tick(word);
+                        cracker::hits(targets, word)
+                    },
+                )
+                .flatten_iter()
+                .collect(),
+            Some(rules) => words
+                .par_iter()
+                .map_init(
+                    || (Vec::new(), monitor.counter()),
+                    |(candidate, counter), word| {
+                        let mut hits = Vec::new();
+                        rules.for_each_candidate(&buf[word.clone()], candidate, |c| {
+                            counter.tick(c);
+                            hits.extend(cracker::hits(targets, c));
+                        });
+                        hits
+                    },
+                )
+                .flatten_iter()
+                .collect(),
+        });
+        sizer.record(words.len() as u64 * rule_count, batch_start.elapsed());
+
+        if cracker.record(hits) {
+            return Ok(());
+        }
+        cracker.checkpoint(Position::Wordlist {
+            bytes: bytes_read,
+            words: words_read,
+        });
+
+        let fraction = total_bytes
+            .filter(|&total| total > 0)
+            .map(|total| bytes_read as f64 / total as f64);
+        cracker.monitor().set_fraction(fraction);
+        cracker.progress(words_read, None, fraction);
+        cracker.wait_while_paused();
+        if cracker.is_cancelled() {
+            return Ok(());
+        }
+    }
+
+    cracker.emit(Event::WordlistComplete { words: words_read });
+    Ok(())
+}
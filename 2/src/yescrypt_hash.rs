@@ -0,0 +1,211 @@
+//! yescrypt (`$y$`), the default of recent Debian, Fedora and Ubuntu
+//! `/etc/shadow` files.
+
+use crate::algorithm::{GenerateOptions, HashAlgorithm, HashOptions, Target};
+use crate::crypt_hash::{ITOA64, push_base64};
+use crate::detect;
+use std::str::FromStr;
+
+pub struct Yescrypt;
+
+/// Bytes of the output the hash records.
+const OUTPUT_LEN: usize = 32;
+
+/// A `$y$params$salt$digest` hash, decoded once but for the digest, which is
+/// compared encoded.
+struct YescryptTarget {
+    params: yescrypt::Params,
+    salt: Vec<u8>,
+    expected: String,
+}
+
+fn parse(hash: &str) -> Result<YescryptTarget, String> {
+    let invalid = |reason: String| format!("Invalid yescrypt hash: {}", reason);
+    let mut parts = hash.strip_prefix("$y$").unwrap_or(hash).split('$');
+    let (Some(params), Some(salt), Some(digest), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid("expected $y$<params>$<salt>$<digest>".to_string()));
+    };
+    let params = yescrypt::Params::from_str(params)
+        .map_err(|e| invalid(format!("parameters '{}': {}", params, e)))?;
+    let salt = decode64(salt).ok_or_else(|| invalid("salt is not crypt base64".to_string()))?;
+    let expected_len = (OUTPUT_LEN * 8).div_ceil(6);
+    if digest.len() != expected_len || !digest.bytes().all(|b| ITOA64.contains(&b)) {
+        return Err(invalid(format!(
+            "digest must be {} characters of crypt base64",
+            expected_len
+        )));
+    }
+    Ok(YescryptTarget {
+        params,
+        salt,
+        expected: digest.to_string(),
+    })
+}
+
+/// Decodes yescrypt's base64: groups of up to four characters, least
+/// significant first, each holding up to three bytes. Bits left over in a
+/// short group must be zero.
+fn decode64(encoded: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(encoded.len() * 3 / 4);
+    for group in encoded.as_bytes().chunks(4) {
+        if group.len() < 2 {
+            return None;
+        }
+        let mut value = 0u32;
+        for (i, c) in group.iter().enumerate() {
+            let digit = ITOA64.iter().position(|x| x == c)? as u32;
+            value |= digit << (6 * i);
+        }
+        let bytes = group.len() * 6 / 8;
+        if value >> (8 * bytes) != 0 {
+            return None;
+        }
+        out.extend_from_slice(&value.to_le_bytes()[..bytes]);
+    }
+    Some(out)
+}
+
+fn encode64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() * 8).div_ceil(6));
+    for group in bytes.chunks(3) {
+        let value = group
+            .iter()
+            .rev()
+            .fold(0u32, |value, &b| value << 8 | b as u32);
+        push_base64(&mut out, value, group.len() + 1);
+    }
+    out
+}
+
+/// Bytes of the random salts of generated hashes, as libxcrypt uses.
+const SALT_LEN: usize = 16;
+
+impl YescryptTarget {
+    /// The encoded digest of `password`, `None` if the parameters are
+    /// unusable.
+    fn hash(&self, password: &[u8]) -> Option<String> {
+        let mut output = [0u8; OUTPUT_LEN];
+        yescrypt::yescrypt(password, &self.salt, &self.params, &mut output).ok()?;
+        Some(encode64(&output))
+    }
+}
+
+/// Hashes `password` with `params` in the notation of the hash, such as
+/// `j9T`. `salt` is written into the hash as is, 16 random bytes when
+/// omitted.
+pub fn hash_yescrypt(password: &[u8], params: &str, salt: Option<&str>) -> Result<String, String> {
+    let parsed = yescrypt::Params::from_str(params)
+        .map_err(|e| format!("Invalid yescrypt parameters '{}': {}", params, e))?;
+    let salt = match salt {
+        Some(salt) => salt.to_string(),
+        None => {
+            let mut bytes = [0u8; SALT_LEN];
+            getrandom::getrandom(&mut bytes)
+                .map_err(|e| format!("Failed to generate a salt: {}", e))?;
+            encode64(&bytes)
+        }
+    };
+    let target = YescryptTarget {
+        params: parsed,
+        salt: decode64(&salt).ok_or("yescrypt salt must be crypt base64")?,
+        expected: String::new(),
+    };
+    let digest = target
+        .hash(password)
+        .ok_or_else(|| format!("yescrypt parameters '{}' are not usable", params))?;
+    Ok(format!("$y${}${}${}", params, salt, digest))
+}
+
+/// Checks `password` against a yescrypt hash.
+pub fn verify_yescrypt(password: &[u8], hash: &str) -> bool {
+    parse(hash).is_ok_and(|target| target.check(password))
+}
+
+impl HashAlgorithm for Yescrypt {
+    fn name(&self) -> &'static str {
+        "yescrypt"
+    }
+
+    fn identify(&self, hash: &str) -> bool {
+        detect::crypt_id(hash) == Some("y") && parse(hash).is_ok()
+    }
+
+    fn prepare(&self, hash: &str, _options: &HashOptions) -> Result<Box<dyn Target>, String> {
+        Ok(Box::new(parse(hash)?))
+    }
+
+    fn generation_error(&self) -> Option<String> {
+        None
+    }
+
+    fn generate(&self, password: &[u8], options: &GenerateOptions) -> Result<String, String> {
+        hash_yescrypt(password, &options.yescrypt_params, options.salt.as_deref())
+    }
+}
+
+impl Target for YescryptTarget {
+    fn check(&self, candidate: &[u8]) -> bool {
+        self.hash(candidate)
+            .is_some_and(|digest| digest == self.expected)
+    }
+
+    /// The `N` blocks of `128 * r` bytes each hash works through.
+    fn memory(&self) -> u64 {
+        128 * self.params.r() as u64 * self.params.n()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hash of "password" by libxcrypt.
+    const HASH: &str = "$y$j9T$abcdefghijklmnop$7asOTx5b6Exfl3myM6K0pLBn.I2hsEvu7G0F7NMfaO.";
+
+    #[test]
+    fn verifies_known_hash() {
+        assert!(Yescrypt.identify(HASH));
+        assert!(verify_yescrypt(b"password", HASH));
+        assert!(!verify_yescrypt(b"Password", HASH));
+        // N = 4096 blocks of 128 * 32 bytes.
+        assert_eq!(parse(HASH).unwrap().memory(), 16 << 20);
+    }
+
+    #[test]
+    fn generates_known_hash() {
+        assert_eq!(
+            hash_yescrypt(b"password", "j9T", Some("abcdefghijklmnop")).unwrap(),
+            HASH
+        );
+        let random = hash_yescrypt(b"password", "j9T", None).unwrap();
+        assert!(verify_yescrypt(b"password", &random), "{}", random);
+    }
+
+    #[test]
+    fn round_trips_base64() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        for len in [1, 2, 3, 16, 32, 256] {
+            assert_eq!(decode64(&encode64(&bytes[..len])).unwrap(), &bytes[..len]);
+        }
+        // Bits past the last byte must be zero, and a lone character holds
+        // none.
+        assert!(decode64("/.").is_some());
+        assert!(decode64("zz").is_none());
+        assert!(decode64("abcde").is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_hashes() {
+        for hash in [
+            "$y$zz$abcdefghijklmnop$7asOTx5b6Exfl3myM6K0pLBn.I2hsEvu7G0F7NMfaO.",
+            "$y$j9T$abc!$7asOTx5b6Exfl3myM6K0pLBn.I2hsEvu7G0F7NMfaO.",
+            "$y$j9T$abcdefghijklmnop$7asOTx5b6Exfl3myM6K0pLBn.I2hsEvu7G0F7NMfaO",
+            "$y$j9T$abcdefghijklmnop",
+        ] {
+            assert!(parse(hash).is_err(), "{}", hash);
+            assert!(!Yescrypt.identify(hash), "{}", hash);
+        }
+    }
+}